@@ -51,6 +51,24 @@ pub enum ucl_emitter_t {
     UCL_EMIT_MAX
 }
 
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum ucl_duplicate_strategy {
+    UCL_DUPLICATE_APPEND = 0,
+    UCL_DUPLICATE_MERGE,
+    UCL_DUPLICATE_REWRITE,
+    UCL_DUPLICATE_ERROR
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum ucl_parse_type {
+    UCL_PARSE_UCL = 0,
+    UCL_PARSE_MSGPACK,
+    UCL_PARSE_CSEXP,
+    UCL_PARSE_AUTO
+}
+
 bitflags! {
 #[repr(C)]
     pub struct ucl_parser_flags_t: c_int {
@@ -59,6 +77,7 @@ bitflags! {
         const UCL_PARSER_ZEROCOPY = 0x2;
         const UCL_PARSER_NO_TIME = 0x4;
         const UCL_PARSER_NO_IMPLICIT_ARRAYS = 0x8;
+        const UCL_PARSER_SAVE_COMMENTS = 0x10;
     }
 }
 
@@ -183,13 +202,16 @@ extern {
     pub fn ucl_parser_add_string(parser: *mut ucl_parser, data: *const c_char, len: size_t) -> bool;
     pub fn ucl_parser_add_file(parser: *mut ucl_parser, filename: *const c_char) -> bool;
     pub fn ucl_parser_get_object(parser: *mut ucl_parser) -> *mut ucl_object_t;
+    pub fn ucl_parser_get_comments(parser: *mut ucl_parser) -> *const ucl_object_t;
     pub fn ucl_parser_get_error(parser: *mut ucl_parser) -> *const c_char;
     pub fn ucl_parser_free(parser: *mut ucl_parser);
     pub fn ucl_parser_set_filevars(parser: *mut ucl_parser, filename: *const c_char, need_expand: bool) -> bool;
     pub fn ucl_parser_set_default_priority(parser: *mut ucl_parser, prio: c_uint) -> bool;
+    pub fn ucl_parser_get_default_priority(parser: *mut ucl_parser) -> c_int;
     pub fn ucl_parser_set_variables_handler(parser: *mut ucl_parser, handler: ucl_variable_handler, ud: *mut c_void);
     pub fn ucl_parser_add_chunk_priority(parser: *mut ucl_parser, data: *const c_uchar, len: size_t, prio: c_uint) -> bool;
     pub fn ucl_parser_add_string_priority(parser: *mut ucl_parser, data: *const c_uchar, len: size_t, prio: c_uint) -> bool;
+    pub fn ucl_parser_add_chunk_full(parser: *mut ucl_parser, data: *const c_uchar, len: size_t, prio: c_uint, strat: ucl_duplicate_strategy, parse_type: ucl_parse_type) -> bool;
     pub fn ucl_parser_add_file_priority(parser: *mut ucl_parser, filename: *const c_uchar, prio: c_uint) -> bool;
     pub fn ucl_parser_add_fd(parser: *mut ucl_parser, fd: c_int) -> bool;
     pub fn ucl_parser_add_fd_priority(parser: *mut ucl_parser, fd: c_int, prio: c_uint) -> bool;
@@ -197,6 +219,7 @@ extern {
     pub fn ucl_parser_get_error_code(parser: *mut ucl_parser) -> c_int;
     pub fn ucl_parser_get_error_column(parser: *mut ucl_parser) -> c_uint;
     pub fn ucl_parser_get_error_linenum(parser: *mut ucl_parser) -> c_uint;
+    pub fn ucl_parser_get_cur_file(parser: *mut ucl_parser) -> *const c_char;
 
     // Pubkey
     pub fn ucl_pubkey_add(parser: *mut ucl_parser, key: *const c_char, len: size_t) -> bool;
@@ -208,11 +231,11 @@ extern {
     // UCL_EXTERN struct ucl_emitter_functions* ucl_object_emit_file_funcs (
     // UCL_EXTERN struct ucl_emitter_functions* ucl_object_emit_fd_funcs (
     // UCL_EXTERN void ucl_object_emit_streamline_start_container (
-    // UCL_EXTERN void ucl_object_emit_streamline_add_object (
+    pub fn ucl_object_emit_streamline_add_object(ctx: *mut ucl_emitter_context, obj: *const ucl_object_t);
     // UCL_EXTERN void ucl_object_emit_streamline_end_container (
-    // UCL_EXTERN void ucl_object_emit_streamline_finish (
+    pub fn ucl_object_emit_streamline_finish(ctx: *mut ucl_emitter_context);
     pub fn ucl_object_emit_funcs_free(f: *mut ucl_emitter_functions);
-    // UCL_EXTERN struct ucl_emitter_context* ucl_object_emit_streamline_new (
+    pub fn ucl_object_emit_streamline_new(obj: *const ucl_object_t, emit_type: ucl_emitter_t, emitter: *mut ucl_emitter_functions) -> *mut ucl_emitter_context;
 
     // Conversion functions
     pub fn ucl_object_toboolean(obj: *const ucl_object_t) -> bool;
@@ -246,20 +269,20 @@ extern {
     pub fn ucl_object_type(obj: *const ucl_object_t) -> ucl_type_t;
 
     // Object manipulation
-    // UCL_EXTERN bool ucl_object_insert_key (ucl_object_t *top, ucl_object_t *elt,
-    // UCL_EXTERN bool ucl_object_replace_key (ucl_object_t *top, ucl_object_t *elt,
+    pub fn ucl_object_insert_key(top: *mut ucl_object_t, elt: *mut ucl_object_t, key: *const c_char, keylen: size_t, copy_key: bool) -> bool;
+    pub fn ucl_object_replace_key(top: *mut ucl_object_t, elt: *mut ucl_object_t, key: *const c_char, keylen: size_t, copy_key: bool) -> bool;
     pub fn ucl_object_merge(top: *mut ucl_object_t, elt: *mut ucl_object_t, copy: bool) -> bool;
-    // UCL_EXTERN bool ucl_object_delete_keyl (ucl_object_t *top,
-    // UCL_EXTERN bool ucl_object_delete_key (ucl_object_t *top,
+    pub fn ucl_object_delete_keyl(top: *mut ucl_object_t, key: *const c_char, keylen: size_t) -> bool;
+    pub fn ucl_object_delete_key(top: *mut ucl_object_t, key: *const c_char) -> bool;
     // UCL_EXTERN ucl_object_t* ucl_object_pop_keyl (ucl_object_t *top, const char *key,
     pub fn ucl_object_pop_key(top: *mut ucl_object_t, key: *const c_char) -> *mut ucl_object_t;
     // UCL_EXTERN bool ucl_object_insert_key_merged (ucl_object_t *top, ucl_object_t *elt,
 
     // Array manipulation
-    // UCL_EXTERN bool ucl_array_append (ucl_object_t *top,
-    // UCL_EXTERN bool ucl_array_prepend (ucl_object_t *top,
+    pub fn ucl_array_append(top: *mut ucl_object_t, elt: *mut ucl_object_t) -> bool;
+    pub fn ucl_array_prepend(top: *mut ucl_object_t, elt: *mut ucl_object_t) -> bool;
     // UCL_EXTERN bool ucl_array_merge (ucl_object_t *top, ucl_object_t *elt,
-    // UCL_EXTERN ucl_object_t* ucl_array_delete (ucl_object_t *top,
+    pub fn ucl_array_delete(top: *mut ucl_object_t, elt: *mut ucl_object_t) -> *mut ucl_object_t;
     pub fn ucl_array_size (top: *const ucl_object_t) -> usize;
     pub fn ucl_array_head(top: *const ucl_object_t) -> *mut ucl_object_t;
     pub fn ucl_array_tail(top: *const ucl_object_t) -> *mut ucl_object_t;
@@ -284,9 +307,11 @@ extern {
     pub fn ucl_object_keyl(obj: *const ucl_object_t, len: *mut size_t) -> *const c_char;
     pub fn ucl_object_ref(obj: *const ucl_object_t) -> *mut ucl_object_t;
     pub fn ucl_object_unref(obj: *mut ucl_object_t);
-    // UCL_EXTERN int ucl_object_compare (const ucl_object_t *o1,
+    pub fn ucl_object_compare(o1: *const ucl_object_t, o2: *const ucl_object_t) -> c_int;
     // UCL_EXTERN void ucl_object_array_sort (ucl_object_t *ar,
     pub fn ucl_object_get_priority(obj: *const ucl_object_t) -> c_uint;
     // UCL_EXTERN void ucl_object_set_priority (ucl_object_t *obj,
     pub fn ucl_object_validate (schema: *const ucl_object_t, obj: *const ucl_object_t, err: *mut ucl_schema_error) -> bool;
+    pub fn ucl_object_validate_root (schema: *const ucl_object_t, obj: *const ucl_object_t, root: *const ucl_object_t, err: *mut ucl_schema_error) -> bool;
+    pub fn ucl_object_validate_root_ext (schema: *const ucl_object_t, obj: *const ucl_object_t, root: *const ucl_object_t, ext_refs: *mut ucl_object_t, err: *mut ucl_schema_error) -> bool;
 }
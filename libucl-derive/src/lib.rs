@@ -0,0 +1,347 @@
+//! `#[derive(UclSchema)]` for [libucl](https://crates.io/crates/libucl).
+//!
+//! Generates JSON schema matching the way a type is read from UCL with `libucl::from_object`.
+//! Doc comments become `description`s, and `rename`, `rename_all`, `default` and `skip` serde
+//! attributes are honoured, so the schema keeps in sync with deserialization. Attributes changing
+//! layout in other ways (`flatten`, `tag`, `content`, `untagged` and `rename_all` of variants)
+//! are rejected.
+//!
+//! Use it through `derive` feature of `libucl` rather than directly.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use] extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as Tokens;
+use syn::ext::IdentExt;
+use syn::{Attribute, Data, DeriveInput, Fields, GenericParam, Lit, Meta, NestedMeta};
+
+#[proc_macro_derive(UclSchema, attributes(serde))]
+pub fn derive_ucl_schema(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(mut input: DeriveInput) -> syn::Result<Tokens> {
+    let container = SerdeAttrs::parse(&input.attrs, Level::Container)?;
+    let body = match input.data {
+        Data::Struct(ref data) => fields_schema(&data.fields, &container)?,
+        Data::Enum(ref data) => {
+            let mut units = Vec::new();
+            let mut variants = Vec::new();
+            for variant in &data.variants {
+                let attrs = SerdeAttrs::parse(&variant.attrs, Level::Variant)?;
+                if attrs.skip {
+                    continue
+                }
+                let name = attrs.rename.unwrap_or_else(|| container.rename_variant(&variant.ident.unraw().to_string()));
+                if let Fields::Unit = variant.fields {
+                    units.push(name);
+                    continue
+                }
+                let inner = describe(fields_schema(&variant.fields, &SerdeAttrs::default())?, &variant.attrs);
+                variants.push(quote! {
+                    ::libucl::object::Builder::object()
+                        .insert("type", "object")
+                        .insert("properties", ::libucl::object::Builder::object().insert(#name, #inner))
+                        .insert("required", ::libucl::object::Builder::array().push(#name))
+                        .insert("additionalProperties", false)
+                });
+            }
+
+            let names = units;
+            let units = quote! {
+                ::libucl::object::Builder::object()
+                    .insert("type", "string")
+                    .insert("enum", ::libucl::object::Builder::array() #(.push(#names))*)
+            };
+            if variants.is_empty() {
+                units
+            } else {
+                let units = if names.is_empty() { vec![] } else { vec![units] };
+                quote! {
+                    ::libucl::object::Builder::object()
+                        .insert("anyOf", ::libucl::object::Builder::array() #(.push(#units))* #(.push(#variants))*)
+                }
+            }
+        },
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(&input.ident, "UclSchema can not be derived for unions"))
+        }
+    };
+    let body = describe(body, &input.attrs);
+
+    for param in input.generics.params.iter_mut() {
+        if let GenericParam::Type(ref mut param) = *param {
+            param.bounds.push(syn::parse_quote!(::libucl::schema::UclSchema));
+        }
+    }
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::libucl::schema::UclSchema for #ident #ty_generics #where_clause {
+            fn ucl_schema() -> ::libucl::object::Builder {
+                #body
+            }
+        }
+    })
+}
+
+/// Schema of struct or enum variant fields.
+fn fields_schema(fields: &Fields, container: &SerdeAttrs) -> syn::Result<Tokens> {
+    match *fields {
+        Fields::Named(ref fields) => {
+            let mut properties = Vec::new();
+            let mut required = Vec::new();
+            for field in &fields.named {
+                let attrs = SerdeAttrs::parse(&field.attrs, Level::Field)?;
+                if attrs.skip {
+                    continue
+                }
+                let ident = field.ident.as_ref().unwrap().unraw().to_string();
+                let name = attrs.rename.unwrap_or_else(|| container.rename_field(&ident));
+                let ty = &field.ty;
+
+                let schema = describe(quote!(<#ty as ::libucl::schema::UclSchema>::ucl_schema()), &field.attrs);
+                properties.push(quote!(.insert(#name, #schema)));
+                if !attrs.default && !container.default {
+                    required.push(quote! {
+                        if !<#ty as ::libucl::schema::UclSchema>::ucl_optional() {
+                            required.push(#name);
+                        }
+                    });
+                }
+            }
+
+            Ok(quote! {{
+                #[allow(unused_mut)]
+                let mut required: Vec<&'static str> = Vec::new();
+                #(#required)*
+                let schema = ::libucl::object::Builder::object()
+                    .insert("type", "object")
+                    .insert("properties", ::libucl::object::Builder::object() #(#properties)*);
+                if required.is_empty() { schema } else { schema.insert("required", required) }
+            }})
+        },
+        Fields::Unnamed(ref fields) if fields.unnamed.len() == 1 => {
+            let ty = &fields.unnamed[0].ty;
+            Ok(quote!(<#ty as ::libucl::schema::UclSchema>::ucl_schema()))
+        },
+        Fields::Unnamed(ref fields) => {
+            let len = fields.unnamed.len() as i64;
+            let items = fields.unnamed.iter().map(|field| {
+                let ty = &field.ty;
+                quote!(.push(<#ty as ::libucl::schema::UclSchema>::ucl_schema()))
+            });
+            Ok(quote! {
+                ::libucl::object::Builder::object()
+                    .insert("type", "array")
+                    .insert("items", ::libucl::object::Builder::array() #(#items)*)
+                    .insert("additionalItems", false)
+                    .insert("minItems", #len)
+                    .insert("maxItems", #len)
+            })
+        },
+        Fields::Unit => Ok(quote!(::libucl::object::Builder::object().insert("type", "null"))),
+    }
+}
+
+/// Wrap schema expression to carry doc comment from `attrs` as description.
+fn describe(schema: Tokens, attrs: &[Attribute]) -> Tokens {
+    let lines: Vec<String> = attrs.iter()
+        .filter(|attr| attr.path.is_ident("doc"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::NameValue(ref meta)) => match meta.lit {
+                Lit::Str(ref doc) => Some(doc.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    let doc = lines.join("\n");
+    let doc = doc.trim();
+
+    if doc.is_empty() {
+        schema
+    } else {
+        quote!(::libucl::schema::describe(#schema, #doc))
+    }
+}
+
+/// Item carrying serde attributes.
+#[derive(Clone, Copy, PartialEq)]
+enum Level {
+    Container,
+    Variant,
+    Field,
+}
+
+/// Serde attributes affecting shape of deserialized value.
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<RenameRule>,
+    default: bool,
+    skip: bool,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[Attribute], level: Level) -> syn::Result<Self> {
+        let mut out = SerdeAttrs::default();
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident("serde")) {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                _ => continue,
+            };
+            for nested in list.nested {
+                let path = match nested {
+                    NestedMeta::Meta(ref meta) => meta.path(),
+                    _ => continue,
+                };
+                // Schema could not describe these without diverging from serde, so they are refused
+                let unsupported = ["flatten", "tag", "content", "untagged"].iter().any(|name| path.is_ident(name))
+                    || (level == Level::Variant && path.is_ident("rename_all"));
+                if unsupported {
+                    let name = path.get_ident().map(|ident| ident.to_string()).unwrap_or_default();
+                    let msg = format!("`#[serde({})]` is not supported by UclSchema", name);
+                    return Err(syn::Error::new_spanned(path, msg))
+                }
+
+                match nested {
+                    NestedMeta::Meta(Meta::NameValue(ref meta)) => {
+                        let value = match meta.lit {
+                            Lit::Str(ref value) => value.value(),
+                            _ => continue,
+                        };
+                        if meta.path.is_ident("rename") {
+                            out.rename = Some(value);
+                        } else if meta.path.is_ident("rename_all") {
+                            match RenameRule::from_str(&value) {
+                                Some(rule) => out.rename_all = Some(rule),
+                                None => return Err(syn::Error::new_spanned(&meta.lit, "unknown rename rule")),
+                            }
+                        } else if meta.path.is_ident("default") {
+                            out.default = true;
+                        }
+                    },
+                    NestedMeta::Meta(Meta::Path(ref path)) => {
+                        if path.is_ident("default") {
+                            out.default = true;
+                        } else if path.is_ident("skip") || path.is_ident("skip_deserializing") {
+                            out.skip = true;
+                        }
+                    },
+                    _ => {}
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    fn rename_field(&self, name: &str) -> String {
+        match self.rename_all {
+            Some(rule) => rule.apply_to_field(name),
+            None => name.to_string(),
+        }
+    }
+
+    fn rename_variant(&self, name: &str) -> String {
+        match self.rename_all {
+            Some(rule) => rule.apply_to_variant(name),
+            None => name.to_string(),
+        }
+    }
+}
+
+/// `rename_all` rule, following `serde_derive` exactly.
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone, Copy)]
+enum RenameRule {
+    LowerCase,
+    UpperCase,
+    PascalCase,
+    CamelCase,
+    SnakeCase,
+    ScreamingSnakeCase,
+    KebabCase,
+    ScreamingKebabCase,
+}
+
+impl RenameRule {
+    fn from_str(rule: &str) -> Option<Self> {
+        let rule = match rule {
+            "lowercase" => RenameRule::LowerCase,
+            "UPPERCASE" => RenameRule::UpperCase,
+            "PascalCase" => RenameRule::PascalCase,
+            "camelCase" => RenameRule::CamelCase,
+            "snake_case" => RenameRule::SnakeCase,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnakeCase,
+            "kebab-case" => RenameRule::KebabCase,
+            "SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebabCase,
+            _ => return None,
+        };
+        Some(rule)
+    }
+
+    /// Rename variant written in `PascalCase`.
+    fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            RenameRule::PascalCase => variant.to_owned(),
+            RenameRule::LowerCase => variant.to_ascii_lowercase(),
+            RenameRule::UpperCase => variant.to_ascii_uppercase(),
+            RenameRule::CamelCase => variant[..1].to_ascii_lowercase() + &variant[1..],
+            RenameRule::SnakeCase => {
+                let mut snake = String::new();
+                for (i, ch) in variant.char_indices() {
+                    if i > 0 && ch.is_uppercase() {
+                        snake.push('_');
+                    }
+                    snake.push(ch.to_ascii_lowercase());
+                }
+                snake
+            },
+            RenameRule::ScreamingSnakeCase => RenameRule::SnakeCase.apply_to_variant(variant).to_ascii_uppercase(),
+            RenameRule::KebabCase => RenameRule::SnakeCase.apply_to_variant(variant).replace('_', "-"),
+            RenameRule::ScreamingKebabCase => {
+                RenameRule::ScreamingSnakeCase.apply_to_variant(variant).replace('_', "-")
+            },
+        }
+    }
+
+    /// Rename field written in `snake_case`.
+    fn apply_to_field(self, field: &str) -> String {
+        match self {
+            RenameRule::LowerCase | RenameRule::SnakeCase => field.to_owned(),
+            RenameRule::UpperCase => field.to_ascii_uppercase(),
+            RenameRule::PascalCase => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            },
+            RenameRule::CamelCase => {
+                let pascal = RenameRule::PascalCase.apply_to_field(field);
+                pascal[..1].to_ascii_lowercase() + &pascal[1..]
+            },
+            RenameRule::ScreamingSnakeCase => field.to_ascii_uppercase(),
+            RenameRule::KebabCase => field.replace('_', "-"),
+            RenameRule::ScreamingKebabCase => RenameRule::ScreamingSnakeCase.apply_to_field(field).replace('_', "-"),
+        }
+    }
+}
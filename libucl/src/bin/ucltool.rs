@@ -7,6 +7,7 @@ use std::fs::File;
 use std::io::{self, Read};
 use std::io::prelude::*;
 use std::path::Path;
+use std::process;
 
 use clap::{App, Arg};
 
@@ -74,12 +75,18 @@ fn main() {
     let parser = libucl::Parser::new();
     let content = parser.parse(content).unwrap();
 
-    if let Some(filename) = matches.value_of("scheme") {
-        let schema = fs::read_to_string(filename)
-            .expect("Something went wrong while reading the file");
-        let parser = libucl::Parser::new();
-        let schema = parser.parse(schema).unwrap();
-        content.validate_with_schema(&schema).unwrap();
+    if let Some(filename) = matches.value_of("schema") {
+        let schema = match libucl::Schema::from_file(filename) {
+            Ok(schema) => schema,
+            Err(err) => {
+                eprintln!("{}: invalid schema: {}", filename, err);
+                process::exit(1);
+            }
+        };
+        if let Err(err) = schema.validate(&content) {
+            eprintln!("{}: {:?}: {}", filename, err.code, err);
+            process::exit(1);
+        }
     }
     match matches.value_of("out") {
         Some(filename) => {
@@ -87,9 +94,9 @@ fn main() {
             let display = path.display();
             let mut file = match File::create(&path) {
                 Err(why) => panic!("couldn't create {}: {}", display, why.description()),
-                Ok(file) => file,
+                Ok(file) => io::BufWriter::new(file),
             };
-            match file.write_all(content.dump_into(format).as_bytes()) {
+            match content.write_to(&mut file, format).and_then(|_| file.flush()) {
                 Err(why) => panic!("couldn't write to {}: {}", display, why.description()),
                 Ok(_) => println!("successfully wrote to {}", display),
             }
@@ -97,8 +104,8 @@ fn main() {
         None => {
             let stdout = io::stdout();
             let mut handle = stdout.lock();
-            handle
-                .write_all(content.dump_into(format).as_bytes())
+            content
+                .write_to(&mut handle, format)
                 .expect("Error writing to stdout");
         }
     };
@@ -0,0 +1,508 @@
+//! Deserialize Rust data structures from UCL objects.
+//!
+//! Available with `serde` feature.
+//!
+//! UCL types are mapped to serde data model as follows:
+//!
+//! - `Type::Object` is a map (or struct); repeated keys are grouped into a sequence
+//! - `Type::Array` is a sequence; any other value is accepted as one element sequence
+//! - `Type::Int`, `Type::Float`, `Type::String` and `Type::Boolean` are respective primitives
+//! - `Type::Time` is `f64` number of seconds
+//! - `Type::Null` is unit or `None`
+//!
+//! Enums can be given either as string (unit variants) or as object with single key.
+
+use std::error;
+use std::fmt;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Unexpected, Visitor};
+
+use error::UclError;
+use object::{iter, Object, Type};
+use parser::Parser;
+
+/// Deserialization error.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error {
+    path: String,
+    msg: String,
+}
+
+impl Error {
+    /// Set path of the error unless it was already set by nested value.
+    fn at(mut self, path: &str) -> Self {
+        if self.path.is_empty() {
+            self.path = path.to_string();
+        }
+        self
+    }
+
+    /// Path to the value that failed, e.g. `section.hosts[1].port`. `None` when error is about
+    /// top level object.
+    pub fn path(&self) -> Option<&str> {
+        if self.path.is_empty() { None } else { Some(self.path.as_ref()) }
+    }
+
+    /// Error message without the path.
+    pub fn message(&self) -> &str {
+        self.msg.as_ref()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path() {
+            Some(path) => write!(f, "{} for key `{}`", self.msg, path),
+            None => write!(f, "{}", self.msg)
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error {
+            path: String::new(),
+            msg: msg.to_string(),
+        }
+    }
+}
+
+impl From<UclError> for Error {
+    fn from(err: UclError) -> Self {
+        de::Error::custom(err)
+    }
+}
+
+/// Parse UCL document and deserialize it into `T`.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::collections::HashMap;
+///
+/// let ports: HashMap<String, u16> = libucl::from_str("http = 80; https = 443").unwrap();
+/// assert_eq!(ports["https"], 443);
+/// ```
+pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Error> {
+    let obj = Parser::new().parse(s)?;
+    from_object(&obj)
+}
+
+/// Deserialize `T` from already parsed object.
+pub fn from_object<T: DeserializeOwned>(obj: &Object) -> Result<T, Error> {
+    T::deserialize(Deserializer::new(obj))
+}
+
+/// Serde deserializer reading from `Object`.
+pub struct Deserializer<'a> {
+    obj: &'a Object,
+    /// Values of repeated key, read as array, when `obj` is the first of them.
+    repeated: &'a [Object],
+    path: &'a str,
+}
+
+impl<'a> Deserializer<'a> {
+    pub fn new(obj: &'a Object) -> Self {
+        Deserializer::at_path(obj, "")
+    }
+
+    fn at_path(obj: &'a Object, path: &'a str) -> Self {
+        Deserializer {
+            obj,
+            repeated: &[],
+            path,
+        }
+    }
+
+    /// Deserializer of all values of one key, which must not be empty.
+    fn values(values: &'a [Object], path: &'a str) -> Self {
+        Deserializer {
+            obj: &values[0],
+            repeated: if values.len() > 1 { values } else { &[] },
+            path,
+        }
+    }
+
+    fn get_type(&self) -> Type {
+        if self.repeated.is_empty() { self.obj.get_type() } else { Type::Array }
+    }
+
+    fn items(&self) -> Vec<Object> {
+        if self.repeated.is_empty() {
+            self.obj.iter().collect()
+        } else {
+            self.repeated.iter().map(Object::new_ref).collect()
+        }
+    }
+
+    fn unexpected(&self) -> Unexpected<'static> {
+        match self.get_type() {
+            Type::Object => Unexpected::Map,
+            Type::Array => Unexpected::Seq,
+            Type::Int => Unexpected::Other("integer"),
+            Type::Float | Type::Time => Unexpected::Other("floating point"),
+            Type::String => Unexpected::Other("string"),
+            Type::Boolean => Unexpected::Other("boolean"),
+            Type::UserData => Unexpected::Other("userdata"),
+            Type::Null => Unexpected::Unit,
+        }
+    }
+
+    fn visit<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.get_type() {
+            Type::Object => visitor.visit_map(MapAccess::new(self.obj, self.path)),
+            Type::Array => visitor.visit_seq(SeqAccess::new(self.items(), self.path)),
+            Type::Int => visitor.visit_i64(self.obj.as_int().unwrap_or_default()),
+            Type::Float => visitor.visit_f64(self.obj.as_float().unwrap_or_default()),
+            Type::Time => visitor.visit_f64(self.obj.as_seconds().unwrap_or_default()),
+            Type::String => visitor.visit_string(self.obj.as_string().unwrap_or_default()),
+            Type::Boolean => visitor.visit_bool(self.obj.as_bool().unwrap_or_default()),
+            Type::UserData | Type::Null => visitor.visit_unit(),
+        }
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for Deserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let path = self.path;
+        self.visit(visitor).map_err(|e| e.at(path))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let path = self.path;
+        match self.get_type() {
+            Type::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }.map_err(|e| e.at(path))
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let path = self.path;
+        match self.get_type() {
+            Type::Array => self.visit(visitor),
+            _ => visitor.visit_seq(SeqAccess::new(vec![self.obj.new_ref()], path)),
+        }.map_err(|e| e.at(path))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize, visitor: V)
+                                                 -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V)
+                                                   -> Result<V::Value, Error> {
+        let path = self.path;
+        visitor.visit_newtype_struct(self).map_err(|e| e.at(path))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str],
+                                         visitor: V) -> Result<V::Value, Error> {
+        let path = self.path;
+        match self.get_type() {
+            Type::String => {
+                let variant = self.obj.as_string().unwrap_or_default();
+                visitor.visit_enum(variant.into_deserializer())
+            },
+            Type::Object => {
+                let mut entries = iter::groups(self.obj).into_iter();
+                match (entries.next(), entries.next()) {
+                    (Some((variant, values)), None) => visitor.visit_enum(EnumAccess { variant, values, path }),
+                    _ => Err(de::Error::invalid_type(self.unexpected(), &"string or object with single key")),
+                }
+            },
+            _ => Err(de::Error::invalid_type(self.unexpected(), &"string or object with single key")),
+        }.map_err(|e| e.at(path))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit
+        unit_struct map struct identifier ignored_any
+    }
+}
+
+/// Access to elements of `Type::Array`, or to single value treated as one element sequence.
+struct SeqAccess<'a> {
+    items: ::std::vec::IntoIter<Object>,
+    index: usize,
+    path: &'a str,
+}
+
+impl<'a> SeqAccess<'a> {
+    fn new(items: Vec<Object>, path: &'a str) -> Self {
+        SeqAccess {
+            items: items.into_iter(),
+            index: 0,
+            path,
+        }
+    }
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for SeqAccess<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        let path = format!("{}[{}]", self.path, self.index);
+        self.index += 1;
+
+        match self.items.next() {
+            Some(item) => seed.deserialize(Deserializer::at_path(&item, &path)).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+/// Access to entries of `Type::Object`.
+struct MapAccess<'a> {
+    entries: ::std::vec::IntoIter<(String, Vec<Object>)>,
+    value: Option<(String, Vec<Object>)>,
+    path: &'a str,
+}
+
+impl<'a> MapAccess<'a> {
+    fn new(obj: &Object, path: &'a str) -> Self {
+        MapAccess {
+            entries: iter::groups(obj).into_iter(),
+            value: None,
+            path,
+        }
+    }
+}
+
+impl<'de, 'a> de::MapAccess<'de> for MapAccess<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        let (key, values) = match self.entries.next() {
+            Some(entry) => entry,
+            None => return Ok(None)
+        };
+        let path = child_path(self.path, &key);
+        self.value = Some((path.clone(), values));
+
+        seed.deserialize(key.into_deserializer()).map(Some).map_err(|e: Error| e.at(&path))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (path, values) = self.value.take().expect("next_value_seed called before next_key_seed");
+
+        seed.deserialize(Deserializer::values(&values, &path))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// Access to enum given as object with single key.
+struct EnumAccess<'a> {
+    variant: String,
+    values: Vec<Object>,
+    path: &'a str,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for EnumAccess<'a> {
+    type Error = Error;
+    type Variant = VariantAccess;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, VariantAccess), Error> {
+        let path = child_path(self.path, &self.variant);
+        let key: de::value::StringDeserializer<Error> = self.variant.into_deserializer();
+        let variant = seed.deserialize(key)?;
+
+        Ok((variant, VariantAccess { values: self.values, path }))
+    }
+}
+
+struct VariantAccess {
+    values: Vec<Object>,
+    path: String,
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        de::Deserialize::deserialize(Deserializer::values(&self.values, &self.path))
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(Deserializer::values(&self.values, &self.path))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(Deserializer::values(&self.values, &self.path), visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V)
+                                       -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(Deserializer::values(&self.values, &self.path), visitor)
+    }
+}
+
+fn child_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeMap, HashMap};
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Host {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Upstream {
+        h2c: bool,
+        connect_timeout: f64,
+        weight: Option<f32>,
+        hosts: Vec<Host>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        tag: String,
+        upstream: Upstream,
+    }
+
+    #[test]
+    fn struct_with_implicit_array() {
+        let cfg: Config = from_str(r#"
+            tag = "svc";
+            upstream {
+                h2c = true;
+                connect_timeout = 1.5s;
+                hosts { host = "localhost"; port = 9000 }
+                hosts { host = "remotehost"; port = 9090 }
+            }
+        "#).unwrap();
+
+        assert_eq!(cfg, Config {
+            tag: "svc".to_string(),
+            upstream: Upstream {
+                h2c: true,
+                connect_timeout: 1.5,
+                weight: None,
+                hosts: vec![
+                    Host { host: "localhost".to_string(), port: 9000 },
+                    Host { host: "remotehost".to_string(), port: 9090 },
+                ],
+            },
+        });
+    }
+
+    #[test]
+    fn repeated_keys_reuse() {
+        let obj = Parser::new().parse(r#"
+            hosts { host = "a"; port = 1 }
+            hosts { host = "b"; port = 2 }
+            mode { limit = 1; limit = 2 }
+        "#).unwrap();
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(rename_all = "lowercase")]
+        enum Mode {
+            Limit(Vec<u32>),
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Hosts {
+            hosts: Vec<Host>,
+            mode: Mode,
+        }
+
+        let first: Hosts = from_object(&obj).unwrap();
+        assert_eq!(first.hosts[1], Host { host: "b".to_string(), port: 2 });
+        assert_eq!(first.mode, Mode::Limit(vec![1, 2]));
+        assert_eq!(from_object::<Hosts>(&obj).unwrap(), first);
+        assert_eq!(obj.entries(true).filter(|(key, _)| key == "hosts").count(), 2);
+    }
+
+    #[test]
+    fn single_value_as_sequence() {
+        let hosts: HashMap<String, Vec<Host>> = from_str("hosts { host = a; port = 1 }").unwrap();
+        assert_eq!(hosts["hosts"], vec![Host { host: "a".to_string(), port: 1 }]);
+    }
+
+    #[test]
+    fn from_parsed_object() {
+        let obj = Parser::new().parse("section { a = 1; b = [1, 2.5, null] }").unwrap();
+        let section = obj.fetch("section").unwrap();
+
+        assert!(from_object::<BTreeMap<String, i64>>(&obj).is_err());
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Section {
+            a: i64,
+            b: Vec<Option<f64>>,
+        }
+        assert_eq!(from_object::<Section>(&section).unwrap(), Section { a: 1, b: vec![Some(1.0), Some(2.5), None] });
+    }
+
+    #[test]
+    fn enums() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(rename_all = "lowercase")]
+        enum Mode {
+            Off,
+            Limit(u32),
+            Range { min: u32, max: u32 },
+        }
+
+        let modes: Vec<Mode> = from_str("modes = [off, { limit = 10 }, { range { min = 1; max = 2 } }]")
+            .map(|mut m: HashMap<String, Vec<Mode>>| m.remove("modes").unwrap())
+            .unwrap();
+        assert_eq!(modes, vec![Mode::Off, Mode::Limit(10), Mode::Range { min: 1, max: 2 }]);
+        assert!(from_str::<HashMap<String, Mode>>("mode { off = true; limit = 1 }").is_err());
+        assert!(from_str::<HashMap<String, Mode>>("mode {}").is_err());
+    }
+
+    #[test]
+    fn error_path() {
+        let err = from_str::<Config>(r#"
+            tag = "svc";
+            upstream {
+                h2c = true;
+                connect_timeout = 1s;
+                hosts { host = "localhost"; port = 9000 }
+                hosts { host = "remotehost"; port = "http" }
+            }
+        "#).unwrap_err();
+        assert_eq!(err.path(), Some("upstream.hosts[1].port"));
+        assert_eq!(err.to_string(), "invalid type: string \"http\", expected u16 for key `upstream.hosts[1].port`");
+
+        let err = from_str::<Config>("tag = svc").unwrap_err();
+        assert_eq!(err.path(), None);
+        assert_eq!(err.message(), "missing field `upstream`");
+
+        let err = from_str::<Config>("tag = svc; upstream { h2c = true }").unwrap_err();
+        assert_eq!(err.path(), Some("upstream"));
+    }
+
+    #[test]
+    fn parse_error() {
+        let err = from_str::<Config>("tag = ").unwrap_err();
+        assert_eq!(err.path(), None);
+        assert!(!err.message().is_empty());
+    }
+}
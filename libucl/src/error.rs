@@ -1,9 +1,12 @@
 use std::error::Error;
 use std::fmt;
+use std::io;
 
 use libucl_bind::{ucl_error_t, ucl_schema_error_code};
 
-#[derive(Clone, Debug)]
+use object::{Object, Type};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum UclErrorType {
     Ok,
     Syntax,
@@ -19,15 +22,15 @@ pub enum UclErrorType {
 impl UclErrorType {
     pub fn from_code(num: i32, desc: String) -> UclError {
         match num {
-            _ if num == ucl_error_t::UCL_EOK       as i32 => UclError{code: UclErrorType::Ok, desc: desc},
-            _ if num == ucl_error_t::UCL_ESYNTAX   as i32 => UclError{code: UclErrorType::Syntax, desc: desc},
-            _ if num == ucl_error_t::UCL_EIO       as i32 => UclError{code: UclErrorType::Io, desc: desc},
-            _ if num == ucl_error_t::UCL_ESTATE    as i32 => UclError{code: UclErrorType::State, desc: desc},
-            _ if num == ucl_error_t::UCL_ENESTED   as i32 => UclError{code: UclErrorType::Nested, desc: desc},
-            _ if num == ucl_error_t::UCL_EMACRO    as i32 => UclError{code: UclErrorType::Macro, desc: desc},
-            _ if num == ucl_error_t::UCL_EINTERNAL as i32 => UclError{code: UclErrorType::Internal, desc: desc},
-            _ if num == ucl_error_t::UCL_ESSL      as i32 => UclError{code: UclErrorType::SSL, desc: desc},
-            _ => UclError{code: UclErrorType::Other, desc: desc}
+            _ if num == ucl_error_t::UCL_EOK       as i32 => UclError::new(UclErrorType::Ok, desc),
+            _ if num == ucl_error_t::UCL_ESYNTAX   as i32 => UclError::new(UclErrorType::Syntax, desc),
+            _ if num == ucl_error_t::UCL_EIO       as i32 => UclError::new(UclErrorType::Io, desc),
+            _ if num == ucl_error_t::UCL_ESTATE    as i32 => UclError::new(UclErrorType::State, desc),
+            _ if num == ucl_error_t::UCL_ENESTED   as i32 => UclError::new(UclErrorType::Nested, desc),
+            _ if num == ucl_error_t::UCL_EMACRO    as i32 => UclError::new(UclErrorType::Macro, desc),
+            _ if num == ucl_error_t::UCL_EINTERNAL as i32 => UclError::new(UclErrorType::Internal, desc),
+            _ if num == ucl_error_t::UCL_ESSL      as i32 => UclError::new(UclErrorType::SSL, desc),
+            _ => UclError::new(UclErrorType::Other, desc)
         }
     }
 }
@@ -35,7 +38,58 @@ impl UclErrorType {
 #[derive(Clone, Debug)]
 pub struct UclError {
     code: UclErrorType,
-    desc: String
+    desc: String,
+    file: Option<String>,
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+impl UclError {
+    pub(crate) fn new(code: UclErrorType, desc: String) -> Self {
+        UclError {
+            code,
+            desc,
+            file: None,
+            line: None,
+            column: None,
+        }
+    }
+
+    /// Attach position of the error inside parsed input. Line `0` means that libucl does not know
+    /// the position, in which case both line and column are left empty.
+    pub(crate) fn with_position(mut self, file: Option<String>, line: usize, column: usize) -> Self {
+        self.file = file;
+        if line > 0 {
+            self.line = Some(line);
+            self.column = Some(column);
+        }
+        self
+    }
+
+    /// Kind of the error.
+    pub fn code(&self) -> UclErrorType {
+        self.code
+    }
+
+    /// Human readable error message as reported by libucl.
+    pub fn desc(&self) -> &str {
+        self.desc.as_ref()
+    }
+
+    /// File that was being parsed when error occurred, `None` when parsing in-memory data.
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_ref().map(|f| f.as_ref())
+    }
+
+    /// Line (counted from 1) at which error occurred.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    /// Column at which error occurred.
+    pub fn column(&self) -> Option<usize> {
+        self.column
+    }
 }
 
 
@@ -55,6 +109,12 @@ impl Error for UclError {
     }
 }
 
+impl From<io::Error> for UclError {
+    fn from(err: io::Error) -> Self {
+        UclError::new(UclErrorType::Io, err.to_string())
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum UclSchemaErrorType {
     Ok,
@@ -63,21 +123,24 @@ pub enum UclSchemaErrorType {
     MissingProperty,
     Constraint,
     MissingDependency,
+    Unknown,
     Other,
 }
 
 
 impl UclSchemaErrorType {
     pub fn from_code(num: i32, desc: String) -> UclSchemaError {
-        match num {
-            _ if num == ucl_schema_error_code::UCL_SCHEMA_OK as i32=> UclSchemaError { code: UclSchemaErrorType::Ok, desc },
-            _ if num == ucl_schema_error_code::UCL_SCHEMA_TYPE_MISMATCH as i32 => UclSchemaError { code: UclSchemaErrorType::TypeMismatch, desc },
-            _ if num == ucl_schema_error_code::UCL_SCHEMA_INVALID_SCHEMA as i32=> UclSchemaError { code: UclSchemaErrorType::InvalidSchema, desc },
-            _ if num == ucl_schema_error_code::UCL_SCHEMA_MISSING_PROPERTY  as i32=> UclSchemaError { code: UclSchemaErrorType::MissingProperty, desc },
-            _ if num == ucl_schema_error_code::UCL_SCHEMA_CONSTRAINT  as i32=> UclSchemaError { code: UclSchemaErrorType::Constraint, desc },
-            _ if num == ucl_schema_error_code::UCL_SCHEMA_MISSING_DEPENDENCY  as i32=> UclSchemaError { code: UclSchemaErrorType::MissingDependency, desc },
-            _ => UclSchemaError { code: UclSchemaErrorType::Other, desc }
-        }
+        let code = match num {
+            _ if num == ucl_schema_error_code::UCL_SCHEMA_OK as i32 => UclSchemaErrorType::Ok,
+            _ if num == ucl_schema_error_code::UCL_SCHEMA_TYPE_MISMATCH as i32 => UclSchemaErrorType::TypeMismatch,
+            _ if num == ucl_schema_error_code::UCL_SCHEMA_INVALID_SCHEMA as i32 => UclSchemaErrorType::InvalidSchema,
+            _ if num == ucl_schema_error_code::UCL_SCHEMA_MISSING_PROPERTY as i32 => UclSchemaErrorType::MissingProperty,
+            _ if num == ucl_schema_error_code::UCL_SCHEMA_CONSTRAINT as i32 => UclSchemaErrorType::Constraint,
+            _ if num == ucl_schema_error_code::UCL_SCHEMA_MISSING_DEPENDENCY as i32 => UclSchemaErrorType::MissingDependency,
+            _ if num == ucl_schema_error_code::UCL_SCHEMA_UNKNOWN as i32 => UclSchemaErrorType::Unknown,
+            _ => UclSchemaErrorType::Other
+        };
+        UclSchemaError { code, desc, path: None }
     }
 }
 
@@ -85,12 +148,38 @@ impl UclSchemaErrorType {
 pub struct UclSchemaError {
     pub code: UclSchemaErrorType,
     pub desc: String,
+    /// Path to failed element from validated root, like `section.hosts[1].port`. Empty for the
+    /// root itself and `None` if element is not part of validated document. Errors reported by
+    /// `Schema` loading carry path inside of the schema instead.
+    pub path: Option<String>,
 }
 
+impl UclSchemaError {
+    /// Element that failed validation, looked up by `path` in `root`, which is the validated
+    /// document (or the schema, for errors reported while loading `Schema`).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let doc = libucl::Parser::new().parse("hosts [ { port = 80 }, { port = http } ]").unwrap();
+    /// let schema = libucl::Parser::new().parse(r#"{"properties": {"hosts": {"items": {
+    ///     "properties": {"port": {"type": "integer"}}
+    /// }}}}"#).unwrap();
+    ///
+    /// let err = doc.validate_with_schema(&schema).unwrap_err();
+    /// assert_eq!(err.element(&doc).unwrap().as_string(), Some("http".to_string()));
+    /// ```
+    pub fn element(&self, root: &Object) -> Option<Object> {
+        root.find_path(self.path.as_ref()?)
+    }
+}
 
 impl fmt::Display for UclSchemaError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.desc)
+        match self.path {
+            Some(ref path) if !path.is_empty() => write!(f, "{} for key `{}`", self.desc, path),
+            _ => write!(f, "{}", self.desc)
+        }
     }
 }
 
@@ -104,3 +193,48 @@ impl Error for UclSchemaError {
     }
 }
 
+
+/// Failed coercion of object value, returned by `Object::to_int_lossy` and similar methods.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UclConversionError {
+    from: Type,
+    target: &'static str,
+    desc: String,
+}
+
+impl UclConversionError {
+    pub(crate) fn new(from: Type, target: &'static str, desc: String) -> Self {
+        UclConversionError { from, target, desc }
+    }
+
+    /// Type of converted object.
+    pub fn from_type(&self) -> Type {
+        self.from
+    }
+
+    /// Name of requested Rust type.
+    pub fn target(&self) -> &str {
+        self.target
+    }
+
+    /// Reason of the failure.
+    pub fn desc(&self) -> &str {
+        self.desc.as_ref()
+    }
+}
+
+impl fmt::Display for UclConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot convert {:?} to {}: {}", self.from, self.target, self.desc)
+    }
+}
+
+impl Error for UclConversionError {
+    fn description(&self) -> &str {
+        self.desc.as_ref()
+    }
+
+    fn cause(&self) -> Option<&dyn Error> {
+        None
+    }
+}
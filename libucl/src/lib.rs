@@ -89,15 +89,33 @@
 //! ```
 
 extern crate libucl_bind;
+extern crate indexmap;
 extern crate libc;
+extern crate regex;
 #[macro_use] extern crate bitflags;
+#[cfg(feature = "serde")]
+#[macro_use] extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+#[macro_use] extern crate serde_derive;
+#[cfg(feature = "derive")]
+#[allow(unused_imports)]
+#[macro_use] extern crate libucl_derive;
 
 
+pub use error::UclConversionError;
 pub use error::UclError;
 pub use error::UclSchemaError;
 pub use parser::Parser;
+pub use schema::{Schema, UclSchema};
+#[cfg(feature = "derive")]
+pub use libucl_derive::UclSchema;
 pub use object::Object;
 pub use object::emitter::Emitter;
+pub use value::Value;
+#[cfg(feature = "serde")]
+pub use de::{from_str, from_object};
+#[cfg(feature = "serde")]
+pub use ser::{to_object, to_string, to_vec};
 
 pub type Result<T> = std::result::Result<T, UclError>;
 
@@ -105,3 +123,9 @@ mod utils;
 pub mod error;
 pub mod parser;
 pub mod object;
+pub mod schema;
+pub mod value;
+#[cfg(feature = "serde")]
+pub mod de;
+#[cfg(feature = "serde")]
+pub mod ser;
@@ -1,14 +1,30 @@
 use libucl_bind::*;
 
-use super::Object;
+use super::{Object, Type};
 
+use std::collections::{BTreeMap, HashMap};
 use std::convert::From;
 use std::ffi::CString;
+use std::hash::Hash;
+use std::time::Duration;
 
 /// Build element object.
 ///
-/// This structure is immutable typed reference to object inside parsed tree. It can be one of
-/// `Type` elements and can be cast only to given type.
+/// Builds scalars (with `From` implementations), as well as objects and arrays.
+///
+/// # Examples
+///
+/// ```rust
+/// use libucl::object::Builder;
+///
+/// let obj = Builder::object()
+///     .insert("name", "svc")
+///     .insert("ports", Builder::array().push(80).push(443))
+///     .insert("tls", Builder::null())
+///     .build();
+///
+/// assert_eq!(obj.fetch_path("ports.1").unwrap().as_int(), Some(443));
+/// ```
 pub struct Builder {
     obj: *mut ucl_object_t
 }
@@ -25,8 +41,65 @@ impl Builder {
         }
     }
 
+    /// Create empty object.
+    pub fn object() -> Self {
+        Builder::from_ptr(unsafe { ucl_object_typed_new(ucl_type_t::UCL_OBJECT) }).unwrap()
+    }
+
+    /// Create empty array.
+    pub fn array() -> Self {
+        Builder::from_ptr(unsafe { ucl_object_typed_new(ucl_type_t::UCL_ARRAY) }).unwrap()
+    }
+
+    /// Create null value.
+    pub fn null() -> Self {
+        Builder::from_ptr(unsafe { ucl_object_typed_new(ucl_type_t::UCL_NULL) }).unwrap()
+    }
+
+    /// Insert `value` under `key`. Has no effect unless builder was created with `object`.
+    ///
+    /// Inserting the same key more than once creates implicit array.
+    pub fn insert<K: AsRef<str>, V: Into<Builder>>(self, key: K, value: V) -> Self {
+        let value = value.into();
+        let k = match CString::new(key.as_ref()) {
+            Ok(k) => k,
+            Err(_) => return self
+        };
+
+        if Type::from(unsafe { ucl_object_type(self.obj) }) == Type::Object {
+            unsafe { ucl_object_insert_key(self.obj, value.into_ptr(), k.as_ptr(), key.as_ref().len(), true) };
+        }
+
+        self
+    }
+
+    /// Add `value` at the end. Has no effect unless builder was created with `array`.
+    pub fn push<V: Into<Builder>>(self, value: V) -> Self {
+        let value = value.into();
+
+        if Type::from(unsafe { ucl_object_type(self.obj) }) == Type::Array {
+            unsafe { ucl_array_append(self.obj, value.into_ptr()) };
+        }
+
+        self
+    }
+
     pub fn build(self) -> Object {
-        Object::from_mut_cptr(self.obj).unwrap()
+        Object::from_mut_cptr(self.into_ptr()).unwrap()
+    }
+
+    /// Release ownership of built object.
+    fn into_ptr(self) -> *mut ucl_object_t {
+        let obj = self.obj;
+        std::mem::forget(self);
+
+        obj
+    }
+}
+
+impl Drop for Builder {
+    fn drop(&mut self) {
+        unsafe { ucl_object_unref(self.obj) }
     }
 }
 
@@ -70,16 +143,58 @@ from_primitive!(bool, ucl_object_frombool);
 
 impl From<String> for Builder {
     fn from(val: String) -> Self {
-
-        let len = val.len();
-        let s = CString::new(val).unwrap();
-        let p = s.as_ptr();
-        Builder::from_ptr(unsafe { ucl_object_fromlstring(p, len as libc::size_t) }).unwrap()
+        From::from(val.as_str())
     }
 }
 
-impl<'a> From<&'a str> for Builder {
+/// Strings are copied with their length, so they may contain NUL bytes.
+impl From<&str> for Builder {
     fn from(val: &str) -> Self {
-        From::from(val.to_string())
+        // libucl measures empty string with `strlen`, so it has to point at NUL terminator
+        let p = if val.is_empty() { b"\0".as_ptr() } else { val.as_ptr() };
+        Builder::from_ptr(unsafe { ucl_object_fromlstring(p as *const libc::c_char, val.len() as libc::size_t) }).unwrap()
+    }
+}
+
+/// Time value, emitted by `EmitOptions::human_durations` with suffixes like `5min`.
+impl From<Duration> for Builder {
+    fn from(val: Duration) -> Self {
+        let repr = format!("{:?}s", val.as_secs_f64());
+        let s = CString::new(repr.as_str()).unwrap();
+        let flags = ucl_string_flags_t::UCL_STRING_PARSE_NUMBER;
+        Builder::from_ptr(unsafe { ucl_object_fromstring_common(s.as_ptr(), repr.len(), flags) }).unwrap()
+    }
+}
+
+impl From<Object> for Builder {
+    fn from(val: Object) -> Self {
+        Builder::from_ptr(val.into_owned_ptr()).unwrap()
+    }
+}
+
+impl<T: Into<Builder>> From<Option<T>> for Builder {
+    fn from(val: Option<T>) -> Self {
+        match val {
+            Some(val) => val.into(),
+            None => Builder::null()
+        }
+    }
+}
+
+impl<T: Into<Builder>> From<Vec<T>> for Builder {
+    fn from(val: Vec<T>) -> Self {
+        val.into_iter().fold(Builder::array(), |arr, v| arr.push(v))
+    }
+}
+
+impl<K: AsRef<str> + Eq + Hash, V: Into<Builder>> From<HashMap<K, V>> for Builder {
+    fn from(val: HashMap<K, V>) -> Self {
+        val.into_iter().fold(Builder::object(), |obj, (k, v)| obj.insert(k, v))
+    }
+}
+
+impl<K: AsRef<str> + Ord, V: Into<Builder>> From<BTreeMap<K, V>> for Builder {
+    fn from(val: BTreeMap<K, V>) -> Self {
+        val.into_iter().fold(Builder::object(), |obj, (k, v)| obj.insert(k, v))
     }
 }
@@ -1,9 +1,10 @@
+use std::io::{self, Write};
+
 use libucl_bind::*;
-use utils;
 
-use super::Object;
+use super::{Builder, EmitSink, Object, Type};
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Emitter {
     JSON,
     JSONCompact,
@@ -14,9 +15,10 @@ pub enum Emitter {
 }
 
 impl Emitter {
+    /// Emit object as string. Returns `None` if output is not valid UTF-8, which is the case for
+    /// `Emitter::MsgPack`; use `Object::emit_bytes` for binary formats.
     pub fn emit<T: AsRef<Object>>(&self, obj: T) -> Option<String> {
-        let emit = unsafe { ucl_object_emit(obj.as_ref().obj, Into::into(*self)) };
-        utils::to_str(emit)
+        String::from_utf8(obj.as_ref().emit_bytes(*self)).ok()
     }
 }
 
@@ -45,3 +47,133 @@ impl Into<ucl_emitter_t> for Emitter {
         }
     }
 }
+
+/// Incremental emitter of single top level array or object.
+///
+/// Elements are written to the underlying writer as soon as they are added, so documents much
+/// larger than available memory can be produced. Streaming is not available for
+/// `Emitter::MsgPack`, which has to know number of elements up front.
+///
+/// Only the top level container is streamed. Nested arrays and objects are written as whole
+/// elements, as libucl does not separate containers opened inside stream from preceding
+/// elements.
+///
+/// # Examples
+///
+/// ```rust
+/// use libucl::object::{Builder, StreamEmitter};
+/// use libucl::Emitter;
+///
+/// let mut stream = StreamEmitter::array(Vec::new(), Emitter::JSONCompact).unwrap();
+/// for i in 0..3 {
+///     stream.add(&Builder::object().insert("id", i).build()).unwrap();
+/// }
+/// let out = stream.finish().unwrap();
+///
+/// assert_eq!(out, br#"[{"id":0},{"id":1},{"id":2}]"#.to_vec());
+/// ```
+pub struct StreamEmitter<W: Write> {
+    ctx: *mut ucl_emitter_context,
+    sink: Option<Box<EmitSink<W>>>,
+    // libucl keeps pointers to both callbacks and container object until stream is finished
+    #[allow(dead_code)]
+    funcs: Box<ucl_emitter_functions>,
+    top: Object,
+}
+
+impl<W: Write> StreamEmitter<W> {
+    /// Start emitting array into `writer`.
+    pub fn array(writer: W, emitter: Emitter) -> io::Result<Self> {
+        StreamEmitter::new(writer, emitter, Builder::array().build())
+    }
+
+    /// Start emitting object into `writer`. Elements are added with `add_entry`.
+    pub fn object(writer: W, emitter: Emitter) -> io::Result<Self> {
+        StreamEmitter::new(writer, emitter, Builder::object().build())
+    }
+
+    fn new(writer: W, emitter: Emitter, top: Object) -> io::Result<Self> {
+        if emitter == Emitter::MsgPack || emitter == Emitter::Max {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "emitter does not support streaming"))
+        }
+
+        let mut sink = Box::new(EmitSink::new(writer));
+        let mut funcs = Box::new(sink.funcs());
+        let ctx = unsafe { ucl_object_emit_streamline_new(top.obj, emitter.into(), &mut *funcs) };
+        if ctx.is_null() {
+            return Err(io::Error::other("failed to start emitter"))
+        }
+
+        let mut stream = StreamEmitter {
+            ctx,
+            sink: Some(sink),
+            funcs,
+            top,
+        };
+        stream.check()?;
+
+        Ok(stream)
+    }
+
+    /// Write `value` as next element.
+    ///
+    /// When emitting object `value` has to have a key, like values fetched from parsed document.
+    /// Use `add_entry` to give it one.
+    pub fn add(&mut self, value: &Object) -> io::Result<()> {
+        if self.top.get_type() == Type::Object && value.key().is_none() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "elements of object require a key"))
+        }
+
+        unsafe { ucl_object_emit_streamline_add_object(self.ctx, value.obj) };
+        self.check()
+    }
+
+    /// Write `value` under `key` as next element of object.
+    pub fn add_entry<K: AsRef<str>, V: Into<Object>>(&mut self, key: K, value: V) -> io::Result<()> {
+        if self.top.get_type() != Type::Object {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "entries can be added only to object"))
+        }
+
+        // Key is assigned by inserting value into scratch object
+        let mut entry = Builder::object().build();
+        if !entry.insert_key(key.as_ref(), value) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid key"))
+        }
+
+        match entry.fetch(key) {
+            Some(value) => self.add(&value),
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid key"))
+        }
+    }
+
+    /// Close the container and return the writer. Writer is not flushed.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.close();
+        self.check()?;
+
+        Ok(self.sink.take().unwrap().writer)
+    }
+
+    /// Report failure of the writer caused by the last operation.
+    fn check(&mut self) -> io::Result<()> {
+        let sink = self.sink.as_mut().unwrap();
+        match sink.take_error() {
+            Some(err) => Err(err),
+            None if sink.failed => Err(io::Error::other("output was interrupted by previous error")),
+            None => Ok(())
+        }
+    }
+
+    fn close(&mut self) {
+        if !self.ctx.is_null() {
+            unsafe { ucl_object_emit_streamline_finish(self.ctx) };
+            self.ctx = std::ptr::null_mut();
+        }
+    }
+}
+
+impl<W: Write> Drop for StreamEmitter<W> {
+    fn drop(&mut self) {
+        self.close()
+    }
+}
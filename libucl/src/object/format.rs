@@ -0,0 +1,403 @@
+use std::io::{self, Write};
+
+use super::{format_double, iter, Emitter, Object, Type};
+
+/// Quoting of keys in `Emitter::Config` output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyQuoting {
+    /// Quote only keys that can not be written as bare words.
+    Auto,
+    /// Quote every key.
+    Always,
+}
+
+/// Separator between key and scalar value in `Emitter::Config` output.
+///
+/// Objects and arrays are always written as `key {` and `key [`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Separator {
+    /// `key = value`
+    Equals,
+    /// `key: value`
+    Colon,
+}
+
+/// Options of pretty printer.
+///
+/// `Emitter::JSON`, `Emitter::JSONCompact` and `Emitter::Config` are formatted in Rust according
+/// to the options, `Emitter::JSONCompact` ignoring those about whitespace. Other formats are
+/// emitted by libucl as is.
+///
+/// Repeated keys are written as repeated keys in `Config` output and as arrays in JSON.
+///
+/// # Examples
+///
+/// ```rust
+/// use libucl::object::{EmitOptions, Separator};
+/// use libucl::Emitter;
+///
+/// let doc = libucl::Parser::new().parse("b = 1; a { c = [1, 2] }").unwrap();
+/// let opts = EmitOptions::new(Emitter::Config)
+///     .indent(2)
+///     .sort_keys(true)
+///     .separator(Separator::Colon)
+///     .array_width(80);
+///
+/// assert_eq!(opts.emit(&doc).unwrap(), "a {\n  c [1, 2]\n}\nb: 1;\n");
+/// ```
+#[derive(Clone, Debug)]
+pub struct EmitOptions {
+    emitter: Emitter,
+    indent: usize,
+    tabs: bool,
+    sort_keys: bool,
+    key_quoting: KeyQuoting,
+    separator: Separator,
+    semicolons: bool,
+    array_width: Option<usize>,
+    human_durations: bool,
+}
+
+impl EmitOptions {
+    /// Create options for given format: four space indentation, keys in document order, bare keys
+    /// and `=` separators with trailing semicolons, and every array element on its own line.
+    pub fn new(emitter: Emitter) -> Self {
+        EmitOptions {
+            emitter,
+            indent: 4,
+            tabs: false,
+            sort_keys: false,
+            key_quoting: KeyQuoting::Auto,
+            separator: Separator::Equals,
+            semicolons: true,
+            array_width: None,
+            human_durations: false,
+        }
+    }
+
+    /// Number of spaces per nesting level. With tabs it is only used as tab width when
+    /// computing array line width.
+    pub fn indent(mut self, width: usize) -> Self {
+        self.indent = width;
+        self
+    }
+
+    /// Indent with tabs instead of spaces.
+    pub fn tabs(mut self, tabs: bool) -> Self {
+        self.tabs = tabs;
+        self
+    }
+
+    /// Write keys of every object in lexicographic order. Values of repeated keys keep their
+    /// order.
+    pub fn sort_keys(mut self, sort: bool) -> Self {
+        self.sort_keys = sort;
+        self
+    }
+
+    /// Quoting of keys in `Config` output.
+    pub fn key_quoting(mut self, quoting: KeyQuoting) -> Self {
+        self.key_quoting = quoting;
+        self
+    }
+
+    /// Separator between keys and scalar values in `Config` output.
+    pub fn separator(mut self, separator: Separator) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    /// Terminate scalar values in `Config` output with semicolons.
+    pub fn semicolons(mut self, semicolons: bool) -> Self {
+        self.semicolons = semicolons;
+        self
+    }
+
+    /// Write arrays of scalars on single line when the whole line fits into `width` columns.
+    pub fn array_width(mut self, width: usize) -> Self {
+        self.array_width = Some(width);
+        self
+    }
+
+    /// Write time values in `Config` output with the largest suffix that keeps them whole, like
+    /// `5min` or `1500ms`, rather than as number of seconds.
+    pub fn human_durations(mut self, human: bool) -> Self {
+        self.human_durations = human;
+        self
+    }
+
+    /// Format `obj` as string. Returns `None` if output is not valid UTF-8, like for
+    /// `Emitter::MsgPack`.
+    pub fn emit(&self, obj: &Object) -> Option<String> {
+        let mut out = Vec::new();
+        self.write(obj, &mut out).ok()?;
+
+        String::from_utf8(out).ok()
+    }
+
+    /// Format `obj` into `writer`.
+    pub fn write<W: Write>(&self, obj: &Object, writer: W) -> io::Result<()> {
+        match self.emitter {
+            Emitter::JSON | Emitter::JSONCompact | Emitter::Config => {
+                Printer { opts: self, out: writer }.write_root(obj)
+            },
+            _ => obj.write_to(writer, self.emitter)
+        }
+    }
+}
+
+struct Printer<'a, W: Write> {
+    opts: &'a EmitOptions,
+    out: W,
+}
+
+impl<'a, W: Write> Printer<'a, W> {
+    fn config(&self) -> bool {
+        self.opts.emitter == Emitter::Config
+    }
+
+    fn compact(&self) -> bool {
+        self.opts.emitter == Emitter::JSONCompact
+    }
+
+    fn write_root(&mut self, obj: &Object) -> io::Result<()> {
+        if self.config() && obj.get_type() == Type::Object {
+            return self.write_members(obj, 0)
+        }
+
+        self.write_value(obj, 0, 0)?;
+        if !self.compact() {
+            self.out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    fn write_indent(&mut self, level: usize) -> io::Result<()> {
+        if self.opts.tabs {
+            self.out.write_all("\t".repeat(level).as_bytes())
+        } else {
+            self.out.write_all(" ".repeat(level * self.opts.indent).as_bytes())
+        }
+    }
+
+    /// Keys of object with all their values, in output order.
+    fn entries(&self, obj: &Object) -> Vec<(String, Vec<Object>)> {
+        let mut entries = iter::groups(obj);
+        if self.opts.sort_keys {
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+        entries
+    }
+
+    fn scalar(&self, obj: &Object) -> Option<String> {
+        match obj.as_seconds() {
+            Some(secs) if self.config() && self.opts.human_durations => Some(duration(secs)),
+            _ => scalar(obj)
+        }
+    }
+
+    fn key(&self, key: &str) -> String {
+        if self.config() && self.opts.key_quoting == KeyQuoting::Auto && is_bare_key(key) {
+            key.to_string()
+        } else {
+            quote(key)
+        }
+    }
+
+    /// Write elements of `Config` object, one per line.
+    fn write_members(&mut self, obj: &Object, level: usize) -> io::Result<()> {
+        for (key, value) in self.entries(obj).into_iter().flat_map(|(key, values)| {
+            values.into_iter().map(move |value| (key.clone(), value))
+        }) {
+            let key = self.key(&key);
+            self.write_indent(level)?;
+            self.out.write_all(key.as_bytes())?;
+
+            let col = level * self.opts.indent + key.len();
+            match self.scalar(&value) {
+                Some(repr) => {
+                    let sep: &[u8] = match self.opts.separator {
+                        Separator::Equals => b" = ",
+                        Separator::Colon => b": ",
+                    };
+                    self.out.write_all(sep)?;
+                    self.out.write_all(repr.as_bytes())?;
+                    if self.opts.semicolons {
+                        self.out.write_all(b";")?;
+                    }
+                },
+                None => {
+                    self.out.write_all(b" ")?;
+                    self.write_value(&value, level, col + 1)?;
+                }
+            }
+            self.out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Write value starting at column `col` of line indented by `level`.
+    fn write_value(&mut self, obj: &Object, level: usize, col: usize) -> io::Result<()> {
+        match obj.get_type() {
+            Type::Object => self.write_object(obj, level),
+            Type::Array => self.write_array(obj, level, col),
+            _ => {
+                let repr = self.scalar(obj).unwrap_or_default();
+                self.out.write_all(repr.as_bytes())
+            }
+        }
+    }
+
+    fn write_object(&mut self, obj: &Object, level: usize) -> io::Result<()> {
+        if self.config() {
+            self.out.write_all(b"{\n")?;
+            self.write_members(obj, level + 1)?;
+            self.write_indent(level)?;
+            return self.out.write_all(b"}")
+        }
+
+        let entries = self.entries(obj);
+        if entries.is_empty() {
+            return self.out.write_all(b"{}")
+        }
+
+        let compact = self.compact();
+        self.out.write_all(if compact { b"{" } else { b"{\n" })?;
+        for (i, (key, values)) in entries.iter().enumerate() {
+            let key = quote(key);
+            if !compact {
+                self.write_indent(level + 1)?;
+            }
+            self.out.write_all(key.as_bytes())?;
+            self.out.write_all(if compact { b":" } else { b": " })?;
+            let col = (level + 1) * self.opts.indent + key.len() + 2;
+            // Values of repeated key are written as array
+            match values.as_slice() {
+                [value] => self.write_value(value, level + 1, col)?,
+                _ => self.write_items(values, level + 1, col)?,
+            }
+            if i + 1 < entries.len() {
+                self.out.write_all(b",")?;
+            }
+            if !compact {
+                self.out.write_all(b"\n")?;
+            }
+        }
+        if !compact {
+            self.write_indent(level)?;
+        }
+        self.out.write_all(b"}")
+    }
+
+    fn write_array(&mut self, obj: &Object, level: usize, col: usize) -> io::Result<()> {
+        let items: Vec<_> = obj.iter().collect();
+        self.write_items(&items, level, col)
+    }
+
+    fn write_items(&mut self, items: &[Object], level: usize, col: usize) -> io::Result<()> {
+        if items.is_empty() {
+            return self.out.write_all(b"[]")
+        }
+
+        if self.compact() {
+            self.out.write_all(b"[")?;
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    self.out.write_all(b",")?;
+                }
+                self.write_value(item, level, 0)?;
+            }
+            return self.out.write_all(b"]")
+        }
+
+        if let Some(line) = self.inline_array(items, col) {
+            return self.out.write_all(line.as_bytes())
+        }
+
+        self.out.write_all(b"[\n")?;
+        for (i, item) in items.iter().enumerate() {
+            self.write_indent(level + 1)?;
+            self.write_value(item, level + 1, (level + 1) * self.opts.indent)?;
+            // Config style terminates every element, JSON only separates them
+            if self.config() || i + 1 < items.len() {
+                self.out.write_all(b",")?;
+            }
+            self.out.write_all(b"\n")?;
+        }
+        self.write_indent(level)?;
+        self.out.write_all(b"]")
+    }
+
+    /// Render array of scalars on single line, if it fits into configured width.
+    fn inline_array(&self, items: &[Object], col: usize) -> Option<String> {
+        let width = self.opts.array_width?;
+        let reprs = items.iter().map(|item| self.scalar(item)).collect::<Option<Vec<_>>>()?;
+        let line = format!("[{}]", reprs.join(", "));
+
+        // Room for trailing `,` or `;`
+        if col + line.chars().count() < width { Some(line) } else { None }
+    }
+}
+
+/// Representation of scalar value, `None` for objects and arrays.
+fn scalar(obj: &Object) -> Option<String> {
+    let repr = match obj.get_type() {
+        Type::Object | Type::Array => return None,
+        Type::Int => obj.as_int().unwrap_or_default().to_string(),
+        Type::Float => format_double(obj.as_float().unwrap_or_default()),
+        Type::Time => format_double(obj.as_seconds().unwrap_or_default()),
+        Type::String => quote(&obj.as_string().unwrap_or_default()),
+        Type::Boolean => obj.as_bool().unwrap_or_default().to_string(),
+        Type::UserData | Type::Null => "null".to_string(),
+    };
+    Some(repr)
+}
+
+/// Time with largest suffix that represents it exactly.
+fn duration(secs: f64) -> String {
+    const UNITS: &[(f64, &str)] = &[(604800.0, "w"), (86400.0, "d"), (3600.0, "h"), (60.0, "min"), (1.0, "s"), (0.001, "ms")];
+
+    if !secs.is_finite() {
+        return format_double(secs)
+    }
+    if secs != 0.0 {
+        for &(size, suffix) in UNITS {
+            let n = secs / size;
+            if n.abs() >= 1.0 && (n - n.round()).abs() < 1e-9 {
+                return format!("{}{}", n.round(), suffix)
+            }
+        }
+    }
+    format!("{:?}s", secs)
+}
+
+/// Key that can be written without quotes.
+fn is_bare_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {},
+        _ => return false
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Quote string using JSON escapes.
+fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
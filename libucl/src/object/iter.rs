@@ -0,0 +1,192 @@
+use std::marker::PhantomData;
+
+use libucl_bind::*;
+
+use super::{Object, Type};
+
+/// Iterator over elements of `Type::Array` object.
+///
+/// Created by `Object::iter`. Each iterator keeps its own position, so the same object can be
+/// iterated any number of times, also in nested loops.
+pub struct Iter<'a> {
+    obj: &'a Object,
+    front: usize,
+    back: usize,
+}
+
+impl<'a> Iter<'a> {
+    pub(crate) fn new(obj: &'a Object) -> Self {
+        Iter {
+            obj,
+            front: 0,
+            back: obj.size(),
+        }
+    }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = Object;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back { return None }
+
+        self.front += 1;
+        self.obj.at(self.front - 1)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<'a> DoubleEndedIterator for Iter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back { return None }
+
+        self.back -= 1;
+        if self.back + 1 == self.obj.size() {
+            Object::from_cptr(unsafe { ucl_array_tail(self.obj.obj) })
+        } else {
+            self.obj.at(self.back)
+        }
+    }
+}
+
+impl<'a> ExactSizeIterator for Iter<'a> {}
+
+/// Iterator over `(key, value)` pairs of `Type::Object` object.
+///
+/// Created by `Object::entries`. Entries are collected when iterator is created, so the object
+/// may be modified (e.g. through another reference) while iterating without affecting the
+/// iteration.
+pub struct Entries<'a> {
+    entries: std::vec::IntoIter<(String, Object)>,
+    _obj: PhantomData<&'a Object>,
+}
+
+impl<'a> Entries<'a> {
+    pub(crate) fn new(obj: &'a Object, expand_values: bool) -> Self {
+        let mut entries = Vec::new();
+        for head in heads(obj) {
+            let key = head.key().unwrap_or_default();
+            let next = unsafe { (*head.obj).next };
+
+            if expand_values {
+                let mut elt = next;
+                entries.push((key.clone(), head));
+                while let Some(obj) = Object::from_cptr(elt) {
+                    elt = unsafe { (*obj.obj).next };
+                    entries.push((key.clone(), obj));
+                }
+            } else if next.is_null() {
+                entries.push((key, head));
+            } else {
+                // Group values of repeated key into explicit array. Values are linked to each
+                // other through `next`, which array slots also use, so the array gets copies.
+                unsafe {
+                    let arr = ucl_object_typed_new(ucl_type_t::UCL_ARRAY);
+                    let mut elt = head.obj as *const ucl_object_t;
+                    while !elt.is_null() {
+                        let copy = detached_copy(elt);
+                        if !copy.is_null() { ucl_array_append(arr, copy); }
+                        elt = (*elt).next;
+                    }
+                    entries.extend(Object::from_mut_cptr(arr).map(|arr| (key, arr)));
+                }
+            }
+        }
+
+        Entries {
+            entries: entries.into_iter(),
+            _obj: PhantomData,
+        }
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = (String, Object);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+/// Iterator over keys of `Type::Object` object.
+///
+/// Created by `Object::keys`. Repeated keys are returned once.
+pub struct Keys<'a> {
+    keys: std::vec::IntoIter<String>,
+    _obj: PhantomData<&'a Object>,
+}
+
+impl<'a> Keys<'a> {
+    pub(crate) fn new(obj: &'a Object) -> Self {
+        Keys {
+            keys: heads(obj).iter().map(|head| head.key().unwrap_or_default()).collect::<Vec<_>>().into_iter(),
+            _obj: PhantomData,
+        }
+    }
+}
+
+impl<'a> Iterator for Keys<'a> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.keys.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+
+/// Every key of `obj` with references to all of its values, in order. Unlike `Entries`, values of
+/// repeated keys are the nodes of the object itself.
+pub(crate) fn groups(obj: &Object) -> Vec<(String, Vec<Object>)> {
+    heads(obj).into_iter().map(|head| {
+        let key = head.key().unwrap_or_default();
+        let mut elt = unsafe { (*head.obj).next };
+        let mut values = vec![head];
+        while let Some(obj) = Object::from_cptr(elt) {
+            elt = unsafe { (*obj.obj).next };
+            values.push(obj);
+        }
+        (key, values)
+    }).collect()
+}
+
+/// References to first value of every key of `obj`, empty for other types than `Type::Object`.
+fn heads(obj: &Object) -> Vec<Object> {
+    let mut heads = Vec::new();
+    if obj.get_type() != Type::Object { return heads }
+
+    // libucl releases iterator state only once iteration reaches the end, so it always runs to
+    // completion here.
+    let mut it: ucl_object_iter_t = std::ptr::null_mut();
+    let mut err = 0;
+    loop {
+        let cur = unsafe { ucl_object_iterate_with_error(obj.obj, &mut it, true, &mut err) };
+        match Object::from_cptr(cur) {
+            Some(head) => heads.push(head),
+            None => return heads,
+        }
+    }
+}
+
+/// Copy of `elt` alone, without values following it in implicit array.
+unsafe fn detached_copy(elt: *const ucl_object_t) -> *mut ucl_object_t {
+    let copy = ucl_object_copy(elt);
+    if copy.is_null() { return copy }
+
+    // Copy of scalar value brings the rest of implicit array along
+    let rest = (*copy).next;
+    (*copy).next = std::ptr::null_mut();
+    (*copy).prev = copy;
+    if !rest.is_null() { ucl_object_unref(rest); }
+    copy
+}
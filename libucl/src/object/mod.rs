@@ -1,6 +1,10 @@
+use std::any::Any;
 use std::convert::From;
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::fmt;
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Duration;
 
 use libc::{
     c_double,
@@ -8,88 +12,146 @@ use libc::{
     c_void,
 };
 
-use error::{UclSchemaError, UclSchemaErrorType};
+use error::{UclConversionError, UclSchemaError, UclSchemaErrorType};
 use libucl_bind::*;
 use utils;
 
 pub use self::builder::Builder;
-pub use self::emitter::Emitter;
+pub use self::emitter::{Emitter, StreamEmitter};
+pub use self::format::{EmitOptions, KeyQuoting, Separator};
+pub use self::iter::{Entries, Iter, Keys};
 pub use self::types::Type;
 
 pub mod types;
 pub mod builder;
 pub mod emitter;
+pub mod format;
+pub mod iter;
 
 #[cfg(test)]
 mod test;
 
 // Helper functions
-extern fn append_char(c: c_uchar, _num_chars: usize, ptr: *mut c_void) -> libc::c_int {
-    assert!(!ptr.is_null());
+/// Destination of emitter callbacks. Remembers first failure, after which output is dropped.
+struct EmitSink<W: Write> {
+    writer: W,
+    failed: bool,
+    error: Option<io::Error>,
+    panic: Option<Box<dyn Any + Send>>,
+}
 
-    unsafe {
-        let tmp = ptr as *mut String;
-        (*tmp).push(c as char);
+impl<W: Write> EmitSink<W> {
+    fn new(writer: W) -> Self {
+        EmitSink {
+            writer,
+            failed: false,
+            error: None,
+            panic: None,
+        }
     }
 
-    0
-}
+    fn write(&mut self, data: &[u8]) -> libc::c_int {
+        if self.failed { return -1 }
+
+        // Unwinding into libucl is undefined behaviour, so panic is resumed once emitter returns.
+        let writer = &mut self.writer;
+        match panic::catch_unwind(AssertUnwindSafe(|| writer.write_all(data))) {
+            Ok(Ok(())) => 0,
+            Ok(Err(err)) => {
+                self.failed = true;
+                self.error = Some(err);
+                -1
+            },
+            Err(payload) => {
+                self.failed = true;
+                self.panic = Some(payload);
+                -1
+            }
+        }
+    }
 
-extern fn append_len(c: *const c_uchar, len: usize, ptr: *mut c_void) -> libc::c_int {
-    assert!(!c.is_null());
-    assert!(!ptr.is_null());
+    /// Callbacks writing into this sink. Sink must not move while they are in use.
+    fn funcs(&mut self) -> ucl_emitter_functions {
+        ucl_emitter_functions {
+            ucl_emitter_append_character: Some(append_char::<W>),
+            ucl_emitter_append_len: Some(append_len::<W>),
+            ucl_emitter_append_int: Some(append_int::<W>),
+            ucl_emitter_append_double: Some(append_double::<W>),
+            ucl_emitter_free_func: None,
+            ud: self as *mut EmitSink<W> as *mut c_void,
+        }
+    }
+
+    /// Take first failure of the writer, resuming its panic. Sink stays failed afterwards.
+    fn take_error(&mut self) -> Option<io::Error> {
+        if let Some(payload) = self.panic.take() {
+            panic::resume_unwind(payload)
+        }
 
-    unsafe {
-        let out = ptr as *mut String;
-        let slice = std::slice::from_raw_parts(c, len);
-        (*out).push_str(std::str::from_utf8(slice).unwrap());
+        self.error.take()
     }
+}
+
+// libucl writes indentation as single character repeated `num_chars` times
+extern fn append_char<W: Write>(c: c_uchar, num_chars: usize, ptr: *mut c_void) -> libc::c_int {
+    assert!(!ptr.is_null());
 
-    0
+    let sink = unsafe { &mut *(ptr as *mut EmitSink<W>) };
+    sink.write(&vec![c; num_chars])
 }
 
-extern fn append_int(i: i64, ptr: *mut c_void) -> libc::c_int {
+extern fn append_len<W: Write>(c: *const c_uchar, len: usize, ptr: *mut c_void) -> libc::c_int {
+    assert!(!c.is_null());
     assert!(!ptr.is_null());
 
-    unsafe {
-        let tmp = ptr as *mut String;
-        let tmp_str = i.to_string();
-        (*tmp).push_str(tmp_str.as_str());
-    }
+    let sink = unsafe { &mut *(ptr as *mut EmitSink<W>) };
+    sink.write(unsafe { std::slice::from_raw_parts(c, len) })
+}
+
+extern fn append_int<W: Write>(i: i64, ptr: *mut c_void) -> libc::c_int {
+    assert!(!ptr.is_null());
 
-    0
+    let sink = unsafe { &mut *(ptr as *mut EmitSink<W>) };
+    sink.write(i.to_string().as_bytes())
 }
 
-extern fn append_double(d: c_double, ptr: *mut c_void) -> libc::c_int {
+extern fn append_double<W: Write>(d: c_double, ptr: *mut c_void) -> libc::c_int {
     assert!(!ptr.is_null());
 
-    unsafe {
-        let tmp = ptr as *mut String;
-        let tmp_str = d.to_string();
-        (*tmp).push_str(tmp_str.as_str());
-    }
+    let sink = unsafe { &mut *(ptr as *mut EmitSink<W>) };
+    sink.write(format_double(d).as_bytes())
+}
 
-    0
+/// Text of floating point number. `Debug` keeps fractional part, so whole numbers are not read
+/// back as integers. NaN and infinities have no representation in JSON or UCL and are written
+/// as `null`.
+fn format_double(d: f64) -> String {
+    if d.is_finite() { format!("{:?}", d) } else { "null".to_string() }
 }
 
 /// File element object.
 ///
-/// This structure is immutable typed reference to object inside parsed tree. It can be one of
-/// `Type` elements and can be cast only to given type.
+/// This structure is typed reference to object inside parsed tree. It can be one of `Type`
+/// elements and can be cast only to given type. Modifications made through one `Object` are
+/// visible through all other references to the same element.
+///
+/// `&mut self` of mutating methods does not make the reference exclusive: `fetch`, `iter` and
+/// the like return further references to the same elements, which stay valid (each holds its
+/// own reference count) but observe all changes, including change of type when null object
+/// becomes an object. Iterators returned by `entries` and `keys` work on snapshot taken when
+/// they are created.
 pub struct Object {
     obj: *mut ucl_object_t,
-    it: ucl_object_iter_t,
-    typ: Type
+    comments: *mut ucl_object_t,
 }
 
 impl Object {
     /// Create new `Object` from const raw pointer. Internal use only.
-    fn from_cptr(obj: *const ucl_object_t) -> Option<Self> {
+    pub(crate) fn from_cptr(obj: *const ucl_object_t) -> Option<Self> {
         if !obj.is_null() {
             Some(Object {
                 obj: unsafe { ucl_object_ref (obj) },
-                it: std::ptr::null_mut(),
-                typ: Type::from(unsafe { ucl_object_type(obj) })
+                comments: std::ptr::null_mut(),
             })
         } else {
             None
@@ -102,23 +164,32 @@ impl Object {
         if !obj.is_null() {
             Some(Object {
                 obj: obj,
-                it: std::ptr::null_mut(),
-                typ: Type::from(unsafe { ucl_object_type(obj) })
+                comments: std::ptr::null_mut(),
             })
         } else {
             None
         }
     }
 
-    fn default_emit_funcs() -> ucl_emitter_functions {
-        ucl_emitter_functions {
-            ucl_emitter_append_character: Some(append_char),
-            ucl_emitter_append_len: Some(append_len),
-            ucl_emitter_append_int: Some(append_int),
-            ucl_emitter_append_double: Some(append_double),
-            ucl_emitter_free_func: None,
-            ud: std::ptr::null_mut(),
+    /// Create new reference to the same element. Internal use only.
+    pub(crate) fn new_ref(&self) -> Self {
+        Object {
+            obj: unsafe { ucl_object_ref(self.obj) },
+            comments: std::ptr::null_mut(),
+        }.with_comments(self.comments)
+    }
+
+    /// Compare values of two objects, including nested elements. Internal use only.
+    pub(crate) fn same_value(&self, other: &Object) -> bool {
+        unsafe { ucl_object_compare(self.obj, other.obj) == 0 }
+    }
+
+    /// Attach comments saved by parser. Internal use only.
+    pub(crate) fn with_comments(mut self, comments: *const ucl_object_t) -> Self {
+        if !comments.is_null() {
+            self.comments = unsafe { ucl_object_ref(comments) };
         }
+        self
     }
 
     // pub fn priority(&self) -> usize {
@@ -133,19 +204,109 @@ impl Object {
     }
 
     pub fn dump(&self) -> String {
-        let out: Box<String> = Box::new(String::new());
-        let mut emit = Self::default_emit_funcs();
+        String::from_utf8_lossy(&self.emit_bytes(Emitter::JSON)).into_owned()
+    }
 
-        unsafe {
-            emit.ud = std::mem::transmute::<Box<String>, *mut c_void>(out);
-            ucl_object_emit_full(self.obj, ucl_emitter_t::UCL_EMIT_JSON, &mut emit, std::ptr::null());
-            let out_final: Box<String> = std::mem::transmute(emit.ud);
-            return *out_final
+    /// Emit object in given format as raw bytes.
+    ///
+    /// Unlike `dump_into` this is binary safe, so it can be used with `Emitter::MsgPack`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libucl::Emitter;
+    ///
+    /// let obj = libucl::Parser::new().parse("a = 1").unwrap();
+    /// assert_eq!(obj.emit_bytes(Emitter::JSONCompact), br#"{"a":1}"#.to_vec());
+    /// ```
+    pub fn emit_bytes(&self, emitter: Emitter) -> Vec<u8> {
+        let mut out: Vec<u8> = Vec::new();
+        // Writing into `Vec` cannot fail, whatever libucl managed to emit is returned
+        let _ = self.write_to(&mut out, emitter);
+
+        out
+    }
+
+    /// Emit object in given format directly into `writer`.
+    ///
+    /// Output is passed to the writer in many small pieces, so wrapping files or sockets into
+    /// `BufWriter` is recommended. Writer is not flushed. First I/O error stops the output and is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libucl::Emitter;
+    ///
+    /// let obj = libucl::Parser::new().parse("a = 1").unwrap();
+    /// let mut out = Vec::new();
+    /// obj.write_to(&mut out, Emitter::JSONCompact).unwrap();
+    ///
+    /// assert_eq!(out, br#"{"a":1}"#.to_vec());
+    /// ```
+    pub fn write_to<W: Write>(&self, writer: W, emitter: Emitter) -> io::Result<()> {
+        self.emit_full(writer, emitter, std::ptr::null())
+    }
+
+    /// Emit object with comments saved by parser into `writer`.
+    ///
+    /// Comments are kept only by documents parsed with `Flags::SAVE_COMMENTS` and are written
+    /// only by `Emitter::Config`. They stay attached to values they were written at, so comments of
+    /// values replaced after parsing are lost.
+    pub fn write_with_comments<W: Write>(&self, writer: W, emitter: Emitter) -> io::Result<()> {
+        self.emit_full(writer, emitter, self.comments)
+    }
+
+    /// Emit object with comments saved by parser as string.
+    ///
+    /// See `write_with_comments` for details.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libucl::parser::Flags;
+    /// use libucl::Emitter;
+    ///
+    /// let doc = libucl::Parser::with_flags(Flags::SAVE_COMMENTS)
+    ///     .parse("# listening port\nport = 80;")
+    ///     .unwrap();
+    ///
+    /// assert!(doc.dump_with_comments(Emitter::Config).contains("# listening port"));
+    /// assert!(!doc.dump_into(Emitter::Config).contains("# listening port"));
+    /// ```
+    pub fn dump_with_comments(&self, emitter: Emitter) -> String {
+        let mut out: Vec<u8> = Vec::new();
+        let _ = self.write_with_comments(&mut out, emitter);
+
+        String::from_utf8_lossy(&out).into_owned()
+    }
+
+    fn emit_full<W: Write>(&self, writer: W, emitter: Emitter, comments: *const ucl_object_t) -> io::Result<()> {
+        let mut sink = EmitSink::new(writer);
+        let mut funcs = sink.funcs();
+        let result = unsafe { ucl_object_emit_full(self.obj, emitter.into(), &mut funcs, comments) };
+
+        match sink.take_error() {
+            Some(err) => Err(err),
+            None if !result => Err(io::Error::other("failed to emit object")),
+            None => Ok(())
         }
     }
 
+    /// Emit object as MessagePack.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let obj = libucl::Parser::new().parse("a = 0").unwrap();
+    /// assert_eq!(obj.to_msgpack(), vec![0x81, 0xa1, b'a', 0x00]);
+    /// ```
+    pub fn to_msgpack(&self) -> Vec<u8> {
+        self.emit_bytes(Emitter::MsgPack)
+    }
+
     pub fn size(&self) -> usize {
-        if self.typ == Type::Array {
+        if self.get_type() == Type::Array {
             return unsafe { ucl_array_size(self.obj) }
         }
 
@@ -153,7 +314,7 @@ impl Object {
     }
 
     pub fn at(&self, i: usize) -> Option<Object> {
-        if self.typ == Type::Array {
+        if self.get_type() == Type::Array {
             unsafe {
                 let out = ucl_array_find_index(self.obj, i);
 
@@ -164,10 +325,19 @@ impl Object {
         None
     }
 
-    pub fn iter_reset(&mut self) {
-        if !self.it.is_null() {
-            self.it = unsafe { ucl_object_iterate_reset(self.it, self.obj) }
-        }
+    /// Iterate over elements of array. Returns empty iterator for other types.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let obj = libucl::Parser::new().parse("a = [1, 2, 3]").unwrap();
+    /// let arr = obj.fetch("a").unwrap();
+    ///
+    /// let items: Vec<_> = arr.iter().rev().map(|o| o.as_int().unwrap()).collect();
+    /// assert_eq!(items, vec![3, 2, 1]);
+    /// ```
+    pub fn iter(&self) -> Iter<'_> {
+        Iter::new(self)
     }
 
     /// Return key assigned to object.
@@ -177,7 +347,7 @@ impl Object {
 
     /// Return type of object.
     pub fn get_type(&self) -> Type {
-        self.typ
+        Type::from(unsafe { ucl_object_type(self.obj) })
     }
 
     /// Return `i64` value
@@ -234,6 +404,180 @@ impl Object {
         }
     }
 
+    /// Return `Type::Time` value in seconds.
+    pub(crate) fn as_seconds(&self) -> Option<f64> {
+        if self.get_type() != Type::Time { return None }
+
+        Some(unsafe { ucl_object_todouble(self.obj) })
+    }
+
+    /// Return time value, like `10s`, `5min` or `1.5h`. Negative times and times too large for
+    /// `Duration` return `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// let obj = libucl::Parser::new().parse("timeout = 1.5min").unwrap();
+    /// assert_eq!(obj.fetch("timeout").unwrap().as_duration(), Some(Duration::from_secs(90)));
+    ///
+    /// let obj = libucl::object::Builder::from(90).build();
+    /// assert_eq!(obj.as_duration(), None);
+    ///
+    /// let obj = libucl::Parser::new().parse("timeout = 1e30s").unwrap();
+    /// assert_eq!(obj.fetch("timeout").unwrap().as_duration(), None);
+    /// ```
+    pub fn as_duration(&self) -> Option<Duration> {
+        Duration::try_from_secs_f64(self.as_seconds()?).ok()
+    }
+
+    /// Return non-negative number as byte count.
+    ///
+    /// Size suffixes are applied by parser, `k`, `m` and `g` multiplying by powers of 1000 and
+    /// `kb`, `mb` and `gb` by powers of 1024.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let obj = libucl::Parser::new().parse("buffer = 10kb; cache = 1Gb; limit = 10k").unwrap();
+    /// assert_eq!(obj.fetch("buffer").unwrap().as_bytes_size(), Some(10240));
+    /// assert_eq!(obj.fetch("cache").unwrap().as_bytes_size(), Some(1 << 30));
+    /// assert_eq!(obj.fetch("limit").unwrap().as_bytes_size(), Some(10000));
+    /// ```
+    pub fn as_bytes_size(&self) -> Option<u64> {
+        match self.get_type() {
+            Type::Int => self.as_int().filter(|&n| n >= 0).map(|n| n as u64),
+            Type::Float => self.as_float()
+                .filter(|&n| n >= 0.0 && n.fract() == 0.0 && n < u64::MAX as f64)
+                .map(|n| n as u64),
+            _ => None
+        }
+    }
+
+    /// Convert scalar to integer. Floats and times are truncated, strings are parsed like unquoted
+    /// numbers, so `"8080"` and `"10k"` are accepted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let doc = libucl::Parser::new().parse(r#"port = "8080"; ratio = 2.7; name = web"#).unwrap();
+    /// assert_eq!(doc.fetch("port").unwrap().to_int_lossy(), Ok(8080));
+    /// assert_eq!(doc.fetch("ratio").unwrap().to_int_lossy(), Ok(2));
+    /// assert!(doc.fetch("name").unwrap().to_int_lossy().is_err());
+    /// ```
+    pub fn to_int_lossy(&self) -> Result<i64, UclConversionError> {
+        let fail = |desc: &str| Err(UclConversionError::new(self.get_type(), "i64", desc.to_string()));
+
+        match self.get_type() {
+            Type::Int => Ok(self.as_int().unwrap_or_default()),
+            Type::Float | Type::Time => {
+                let n = unsafe { ucl_object_todouble(self.obj) }.trunc();
+                if !n.is_finite() || n < i64::MIN as f64 || n >= i64::MAX as f64 {
+                    return fail("value is out of range")
+                }
+                Ok(n as i64)
+            },
+            Type::String => match self.parse_number() {
+                Some(obj) => obj.to_int_lossy().map_err(|err| UclConversionError::new(Type::String, "i64", err.desc().to_string())),
+                None => fail("string is not a number"),
+            },
+            _ => fail("value is not a number"),
+        }
+    }
+
+    /// Convert scalar to float. Times are converted to seconds, strings are parsed like unquoted
+    /// numbers.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let doc = libucl::Parser::new().parse(r#"ratio = 1; timeout = 1.5min; limit = "1.5""#).unwrap();
+    /// assert_eq!(doc.fetch("ratio").unwrap().to_f64(), Ok(1.0));
+    /// assert_eq!(doc.fetch("timeout").unwrap().to_f64(), Ok(90.0));
+    /// assert_eq!(doc.fetch("limit").unwrap().to_f64(), Ok(1.5));
+    /// ```
+    pub fn to_f64(&self) -> Result<f64, UclConversionError> {
+        match self.get_type() {
+            Type::Int => Ok(self.as_int().unwrap_or_default() as f64),
+            Type::Float | Type::Time => Ok(unsafe { ucl_object_todouble(self.obj) }),
+            Type::String => self.parse_number().and_then(|obj| obj.to_f64().ok()).ok_or_else(|| {
+                UclConversionError::new(Type::String, "f64", "string is not a number".to_string())
+            }),
+            typ => Err(UclConversionError::new(typ, "f64", "value is not a number".to_string())),
+        }
+    }
+
+    /// Convert scalar to its textual representation, as produced by libucl.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let doc = libucl::Parser::new().parse("port = 8080; tls = on; name = web").unwrap();
+    /// assert_eq!(doc.fetch("port").unwrap().to_string_forced(), Ok("8080".to_string()));
+    /// assert_eq!(doc.fetch("tls").unwrap().to_string_forced(), Ok("true".to_string()));
+    /// assert_eq!(doc.fetch("name").unwrap().to_string_forced(), Ok("web".to_string()));
+    /// ```
+    pub fn to_string_forced(&self) -> Result<String, UclConversionError> {
+        let fail = |desc: &str| Err(UclConversionError::new(self.get_type(), "String", desc.to_string()));
+
+        match self.get_type() {
+            Type::Object | Type::Array | Type::UserData => fail("value is not a scalar"),
+            _ => {
+                let out = unsafe { ucl_object_tostring_forced(self.obj) };
+                if out.is_null() {
+                    return fail("libucl can not represent value as string")
+                }
+                Ok(unsafe { CStr::from_ptr(out) }.to_string_lossy().into_owned())
+            }
+        }
+    }
+
+    /// Convert scalar to boolean. Strings `true`, `yes` and `on` are `true`, `false`, `no` and `off`
+    /// are `false`, ignoring case. Integers `1` and `0` are accepted as well.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let doc = libucl::Parser::new().parse(r#"a = "Yes"; b = "off"; c = 1; d = "maybe""#).unwrap();
+    /// assert_eq!(doc.fetch("a").unwrap().to_bool(), Ok(true));
+    /// assert_eq!(doc.fetch("b").unwrap().to_bool(), Ok(false));
+    /// assert_eq!(doc.fetch("c").unwrap().to_bool(), Ok(true));
+    /// assert!(doc.fetch("d").unwrap().to_bool().is_err());
+    /// ```
+    pub fn to_bool(&self) -> Result<bool, UclConversionError> {
+        let fail = |desc: &str| Err(UclConversionError::new(self.get_type(), "bool", desc.to_string()));
+
+        match self.get_type() {
+            Type::Boolean => Ok(self.as_bool().unwrap_or_default()),
+            Type::Int => match self.as_int() {
+                Some(0) => Ok(false),
+                Some(1) => Ok(true),
+                _ => fail("integer is neither 0 nor 1"),
+            },
+            Type::String => match self.as_string().unwrap_or_default().trim().to_lowercase().as_str() {
+                "true" | "yes" | "on" => Ok(true),
+                "false" | "no" | "off" => Ok(false),
+                _ => fail("string is not a boolean"),
+            },
+            _ => fail("value is not a boolean"),
+        }
+    }
+
+    /// Parse string value as unquoted number, including size and time suffixes.
+    fn parse_number(&self) -> Option<Object> {
+        let s = self.as_string()?;
+        let s = s.trim();
+        let c = CString::new(s).ok()?;
+        let flags = ucl_string_flags_t::UCL_STRING_PARSE_NUMBER;
+        let obj = Object::from_mut_cptr(unsafe { ucl_object_fromstring_common(c.as_ptr(), s.len(), flags) })?;
+
+        match obj.get_type() {
+            Type::Int | Type::Float | Type::Time => Some(obj),
+            _ => None
+        }
+    }
+
     /// Return boolean value
     ///
     /// # Examples
@@ -276,9 +620,13 @@ impl Object {
 
         if self.get_type() != Type::String { return None }
         unsafe {
-            let out = ucl_object_tostring(self.obj);
+            let mut out = std::ptr::null();
+            let mut len = 0;
+            if !ucl_object_tolstring_safe(self.obj, &mut out, &mut len) || out.is_null() { return None }
 
-            utils::to_str(out)
+            // Built strings may contain NUL bytes, so length is taken from object
+            let bytes = std::slice::from_raw_parts(out as *const u8, len);
+            Some(String::from_utf8_lossy(bytes).into_owned())
         }
     }
 
@@ -303,6 +651,43 @@ impl Object {
         }
     }
 
+    /// Iterate over keys and values of object. Returns empty iterator for other types.
+    ///
+    /// Keys that occur multiple times (implicit arrays) are returned once with array of all
+    /// values, or, when `expand_values` is set, once per each value. Such array holds copies of
+    /// the values, so changes made through it do not reach this object.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let obj = libucl::Parser::new().parse("a = 1; b = 2; b = 3").unwrap();
+    ///
+    /// let grouped: Vec<_> = obj.entries(false).map(|(k, v)| (k, v.size())).collect();
+    /// assert_eq!(grouped, vec![("a".to_string(), 0), ("b".to_string(), 2)]);
+    ///
+    /// let expanded: Vec<_> = obj.entries(true).map(|(k, v)| (k, v.as_int())).collect();
+    /// assert_eq!(expanded, vec![
+    ///     ("a".to_string(), Some(1)),
+    ///     ("b".to_string(), Some(2)),
+    ///     ("b".to_string(), Some(3)),
+    /// ]);
+    /// ```
+    pub fn entries(&self, expand_values: bool) -> Entries<'_> {
+        Entries::new(self, expand_values)
+    }
+
+    /// Iterate over keys of object. Returns empty iterator for other types.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let obj = libucl::Parser::new().parse("a = 1; b = 2; b = 3").unwrap();
+    /// assert_eq!(obj.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+    /// ```
+    pub fn keys(&self) -> Keys<'_> {
+        Keys::new(self)
+    }
+
     /// Fetch object at the end of path delimeted by `.` (dot)
     ///
     /// # Examples
@@ -322,48 +707,260 @@ impl Object {
         }
     }
 
+    /// Insert `value` under `key`. If key is already present, value is added to implicit array.
+    ///
+    /// Null objects are turned into objects. Returns `false` if object is of any other type.
+    ///
+    /// Value that is referenced elsewhere (e.g. fetched from other tree) is inserted as a copy.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut obj = libucl::Parser::new().parse("a = 1").unwrap();
+    /// assert!(obj.insert_key("b", libucl::object::Builder::from(2)));
+    /// assert_eq!(obj.fetch("b").unwrap().as_int(), Some(2));
+    /// ```
+    pub fn insert_key<K: AsRef<str>, V: Into<Object>>(&mut self, key: K, value: V) -> bool {
+        if self.get_type() != Type::Object && self.get_type() != Type::Null { return false }
+
+        // libucl computes length of empty key with `strlen`, so key has to be NUL terminated
+        let k = match CString::new(key.as_ref()) {
+            Ok(k) => k,
+            Err(_) => return false
+        };
+        let elt = value.into().into_owned_ptr();
+        let res = unsafe { ucl_object_insert_key(self.obj, elt, k.as_ptr(), key.as_ref().len(), true) };
+
+        self.finish_insert(elt, res)
+    }
+
+    /// Insert `value` under `key`, replacing all previous values of that key.
+    ///
+    /// See `insert_key` for details.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut obj = libucl::Parser::new().parse("version = 1").unwrap();
+    /// assert!(obj.replace_key("version", libucl::object::Builder::from(2)));
+    /// assert_eq!(obj.fetch("version").unwrap().as_int(), Some(2));
+    /// ```
+    pub fn replace_key<K: AsRef<str>, V: Into<Object>>(&mut self, key: K, value: V) -> bool {
+        if self.get_type() != Type::Object && self.get_type() != Type::Null { return false }
+
+        // libucl computes length of empty key with `strlen`, so key has to be NUL terminated
+        let k = match CString::new(key.as_ref()) {
+            Ok(k) => k,
+            Err(_) => return false
+        };
+        let elt = value.into().into_owned_ptr();
+        let res = unsafe { ucl_object_replace_key(self.obj, elt, k.as_ptr(), key.as_ref().len(), true) };
+
+        self.finish_insert(elt, res)
+    }
+
+    /// Remove all values under `key`. Returns `false` if there was no such key.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut obj = libucl::Parser::new().parse("a = 1; b = 2").unwrap();
+    /// assert!(obj.delete_key("a"));
+    /// assert!(!obj.delete_key("a"));
+    /// assert!(obj.fetch("a").is_none());
+    /// ```
+    pub fn delete_key<K: AsRef<str>>(&mut self, key: K) -> bool {
+        if self.get_type() != Type::Object { return false }
+
+        match CString::new(key.as_ref()) {
+            Ok(k) => unsafe { ucl_object_delete_key(self.obj, k.as_ptr()) },
+            Err(_) => false
+        }
+    }
+
+    /// Add `value` at the end of array. Returns `false` if object is not an array.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let obj = libucl::Parser::new().parse("a = [1]").unwrap();
+    /// let mut arr = obj.fetch("a").unwrap();
+    /// assert!(arr.append(libucl::object::Builder::from(2)));
+    /// assert_eq!(obj.fetch("a").unwrap().at(1).unwrap().as_int(), Some(2));
+    /// ```
+    pub fn append<V: Into<Object>>(&mut self, value: V) -> bool {
+        if self.get_type() != Type::Array { return false }
+
+        let elt = value.into().into_owned_ptr();
+        let res = unsafe { ucl_array_append(self.obj, elt) };
+
+        self.finish_insert(elt, res)
+    }
+
+    /// Add `value` at the beginning of array. Returns `false` if object is not an array.
+    pub fn prepend<V: Into<Object>>(&mut self, value: V) -> bool {
+        if self.get_type() != Type::Array { return false }
+
+        let elt = value.into().into_owned_ptr();
+        let res = unsafe { ucl_array_prepend(self.obj, elt) };
+
+        self.finish_insert(elt, res)
+    }
+
+    /// Remove and return array element at index `i`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let obj = libucl::Parser::new().parse("a = [1, 2, 3]").unwrap();
+    /// let mut arr = obj.fetch("a").unwrap();
+    /// assert_eq!(arr.delete_at(1).unwrap().as_int(), Some(2));
+    /// assert_eq!(arr.size(), 2);
+    /// ```
+    pub fn delete_at(&mut self, i: usize) -> Option<Object> {
+        if self.get_type() != Type::Array { return None }
+
+        unsafe {
+            let elt = ucl_array_find_index(self.obj, i);
+            if elt.is_null() { return None }
+
+            Object::from_mut_cptr(ucl_array_delete(self.obj, elt as *mut ucl_object_t))
+        }
+    }
+
+    /// Release `Object` and return raw pointer that can be placed in another tree. Object that
+    /// is referenced elsewhere is copied, as placing it in tree overwrites its key.
+    fn into_owned_ptr(self) -> *mut ucl_object_t {
+        let obj = if unsafe { (*self.obj).rc } > 1 {
+            unsafe { ucl_object_copy(self.obj) }
+        } else {
+            self.obj
+        };
+
+        if obj == self.obj {
+            if !self.comments.is_null() {
+                unsafe { ucl_object_unref(self.comments) };
+            }
+            std::mem::forget(self);
+        }
+
+        obj
+    }
+
+    fn finish_insert(&mut self, elt: *mut ucl_object_t, res: bool) -> bool {
+        if !res {
+            unsafe { ucl_object_unref(elt) }
+        }
+        res
+    }
+
+    /// Validate object against JSON schema (draft v4). See `Schema` for schemas checked up front
+    /// and reused across many documents.
     pub fn validate_with_schema(&self, schema: &Object) -> Result<(), UclSchemaError> {
+        self.validate_ext(schema, None)
+    }
+
+    /// Validate against `schema`, looking up external `$ref` documents in `ext_refs` object keyed
+    /// by their URI. Internal use only.
+    pub(crate) fn validate_ext(&self, schema: &Object, ext_refs: Option<&Object>) -> Result<(), UclSchemaError> {
         unsafe {
             let mut err = ucl_schema_error {
                 code: ucl_schema_error_code::UCL_SCHEMA_OK,
                 msg: ['\0' as i8; 128],
                 obj: std::ptr::null_mut(),
             };
-            if ucl_object_validate(schema.obj,self.obj, &mut err) {
+            let valid = match ext_refs {
+                Some(refs) => ucl_object_validate_root_ext(schema.obj, self.obj, schema.obj, refs.obj, &mut err),
+                None => ucl_object_validate(schema.obj, self.obj, &mut err),
+            };
+            if valid {
                 return Ok(())
             }
-            Err(UclSchemaErrorType::from_code(err.code as i32, String::from("")))
+            let desc = CStr::from_ptr(err.msg.as_ptr()).to_string_lossy().into_owned();
+            let mut error = UclSchemaErrorType::from_code(err.code as i32, desc);
+            error.path = self.path_to(err.obj);
+            Err(error)
         }
     }
-}
 
-impl Iterator for Object {
-    type Item = super::Object;
-
-    fn next(&mut self) -> Option<Self::Item> {
+    /// Element at `path` in format produced by `path_to`. Internal use only.
+    pub(crate) fn find_path(&self, path: &str) -> Option<Object> {
+        if path.is_empty() {
+            return Some(self.new_ref())
+        }
 
-        if self.it.is_null() {
-           self.it = unsafe { ucl_object_iterate_new(self.obj) }
+        if path.starts_with('[') {
+            let end = path.find(']')?;
+            let rest = &path[end + 1..];
+            let item = self.at(path[1..end].parse().ok()?)?;
+            return item.find_path(rest.strip_prefix('.').unwrap_or(rest))
         }
 
-        if self.typ != Type::Array {
-            return None
+        // Keys may contain `.` or `[` themselves, so every key that is a prefix of path is tried
+        iter::groups(self).into_iter().find_map(|(key, mut values)| {
+            let rest = path.strip_prefix(key.as_str())?;
+            if values.len() == 1 {
+                let value = values.pop()?;
+                return match rest.chars().next() {
+                    None | Some('[') => value.find_path(rest),
+                    Some('.') => value.find_path(&rest[1..]),
+                    _ => None,
+                }
+            }
+
+            // Values of repeated key are indexed like array items
+            let end = rest.strip_prefix('[')?.find(']')? + 1;
+            let value = values.into_iter().nth(rest[1..end].parse().ok()?)?;
+            let rest = &rest[end + 1..];
+            value.find_path(rest.strip_prefix('.').unwrap_or(rest))
+        })
+    }
+
+    /// Find path to element `target` inside of this object, `""` being the object itself.
+    fn path_to(&self, target: *const ucl_object_t) -> Option<String> {
+        if std::ptr::eq(self.obj, target) {
+            return Some(String::new())
         }
 
-        let cur = unsafe { ucl_object_iterate_safe (self.it, true) };
-        if cur.is_null() {
-            return None
+        match self.get_type() {
+            Type::Object => iter::groups(self).into_iter().find_map(|(key, values)| {
+                let single = values.len() == 1;
+                values.into_iter().enumerate().find_map(|(i, value)| {
+                    let rest = value.path_to(target)?;
+                    let head = if single { key.clone() } else { format!("{}[{}]", key, i) };
+                    Some(join_path(&head, &rest))
+                })
+            }),
+            Type::Array => self.iter().enumerate().find_map(|(i, value)| {
+                value.path_to(target).map(|rest| join_path(&format!("[{}]", i), &rest))
+            }),
+            _ => None
         }
+    }
+}
+
+fn join_path(head: &str, rest: &str) -> String {
+    if rest.is_empty() || rest.starts_with('[') {
+        format!("{}{}", head, rest)
+    } else {
+        format!("{}.{}", head, rest)
+    }
+}
+
+impl<'a> IntoIterator for &'a Object {
+    type Item = Object;
+    type IntoIter = Iter<'a>;
 
-        super::Object::from_cptr(cur)
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
     }
 }
 
 impl Drop for Object {
     fn drop(&mut self) {
         unsafe {
-            if !self.it.is_null() { ucl_object_iterate_free(self.it); }
             if !self.obj.is_null() { ucl_object_unref(self.obj); }
+            if !self.comments.is_null() { ucl_object_unref(self.comments); }
         }
     }
 }
@@ -1,3 +1,7 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use std::time::Duration;
+
 use super::*;
 
 #[test]
@@ -30,6 +34,13 @@ fn from_str() {
     assert_eq!(obj.get_type(), Type::String);
 }
 
+#[test]
+fn from_str_with_nul() {
+    let obj = Builder::from("a\0b").build();
+    assert_eq!(obj.as_string(), Some("a\0b".to_string()));
+    assert_eq!(Builder::from("").build().as_string(), Some(String::new()));
+}
+
 #[test]
 fn to_int() {
     let obj = Builder::from(10).build();
@@ -50,3 +61,558 @@ fn to_int_invalid_type() {
 
 
 
+
+#[test]
+fn entries_sections() {
+    let obj = ::Parser::new().parse(r#"
+        section "a" { port = 1; }
+        section "b" { port = 2; }
+        other { port = 3; }
+    "#).unwrap();
+
+    let keys: Vec<_> = obj.keys().collect();
+    assert_eq!(keys, vec!["section", "other"]);
+
+    let sections = obj.fetch("section").unwrap();
+    let ports: Vec<_> = sections.entries(false)
+        .map(|(k, v)| (k, v.fetch("port").and_then(|p| p.as_int())))
+        .collect();
+    assert_eq!(ports, vec![("a".to_string(), Some(1)), ("b".to_string(), Some(2))]);
+}
+
+#[test]
+fn entries_implicit_array() {
+    let obj = ::Parser::new().parse("host = a; host = b; port = 1").unwrap();
+
+    let grouped: Vec<_> = obj.entries(false).collect();
+    assert_eq!(grouped.len(), 2);
+    assert_eq!(grouped[0].0, "host");
+    assert_eq!(grouped[0].1.get_type(), Type::Array);
+    assert_eq!(grouped[0].1.at(1).unwrap().as_string(), Some("b".to_string()));
+
+    let expanded: Vec<_> = obj.entries(true).map(|(k, v)| (k, v.get_type())).collect();
+    assert_eq!(expanded, vec![
+        ("host".to_string(), Type::String),
+        ("host".to_string(), Type::String),
+        ("port".to_string(), Type::Int),
+    ]);
+}
+
+#[test]
+fn entries_implicit_array_drop() {
+    let obj = ::Parser::new().parse("host = a; host = b; host { port = 1 }; port = 1").unwrap();
+
+    let grouped = obj.entries(false).next().unwrap().1;
+    assert_eq!(grouped.size(), 3);
+    assert_eq!(grouped.at(2).unwrap().fetch("port").unwrap().as_int(), Some(1));
+    drop(grouped);
+
+    let hosts: Vec<_> = obj.entries(true).map(|(_, v)| v.get_type()).collect();
+    assert_eq!(hosts, vec![Type::String, Type::String, Type::Object, Type::Int]);
+    assert_eq!(obj.fetch("host").unwrap().as_string(), Some("a".to_string()));
+    assert!(obj.dump_into(Emitter::Config).contains("port = 1;"));
+
+    // Tree is still intact when grouped entries outlive it
+    let grouped: Vec<_> = obj.entries(false).collect();
+    drop(obj);
+    assert_eq!(grouped[0].1.at(1).unwrap().as_string(), Some("b".to_string()));
+}
+
+#[test]
+fn entries_not_object() {
+    let obj = Builder::from(10).build();
+    assert_eq!(obj.entries(true).count(), 0);
+    assert_eq!(obj.keys().count(), 0);
+}
+
+#[test]
+fn entries_early_drop() {
+    let obj = ::Parser::new().parse("a = 1; b = 2; c = 3").unwrap();
+    assert_eq!(obj.entries(false).next().unwrap().0, "a");
+    assert_eq!(obj.entries(false).nth(2).unwrap().0, "c");
+}
+
+#[test]
+fn iter_nested() {
+    let obj = ::Parser::new().parse("a = [1, 2, 3]").unwrap();
+    let arr = obj.fetch("a").unwrap();
+
+    let mut pairs = Vec::new();
+    for x in &arr {
+        for y in &arr {
+            pairs.push((x.as_int().unwrap(), y.as_int().unwrap()));
+        }
+    }
+    assert_eq!(pairs.len(), 9);
+    assert_eq!(pairs[5], (2, 3));
+}
+
+#[test]
+fn iter_double_ended() {
+    let obj = ::Parser::new().parse("a = [1, 2, 3, 4]").unwrap();
+    let arr = obj.fetch("a").unwrap();
+    let mut it = arr.iter();
+
+    assert_eq!(it.len(), 4);
+    assert_eq!(it.next_back().unwrap().as_int(), Some(4));
+    assert_eq!(it.next().unwrap().as_int(), Some(1));
+    assert_eq!(it.len(), 2);
+    assert_eq!(it.next_back().unwrap().as_int(), Some(3));
+    assert_eq!(it.next().unwrap().as_int(), Some(2));
+    assert!(it.next().is_none());
+    assert!(it.next_back().is_none());
+}
+
+#[test]
+fn iter_not_array() {
+    let obj = Builder::from("test_string").build();
+    assert_eq!(obj.iter().len(), 0);
+    assert!(obj.iter().next().is_none());
+}
+
+#[test]
+fn edit_nested() {
+    let mut cfg = ::Parser::new().parse(r#"
+        version = 1;
+        section { flag = true; hosts = [a, b]; }
+    "#).unwrap();
+
+    assert!(cfg.replace_key("version", Builder::from(2)));
+    assert!(cfg.insert_key("name", Builder::from("svc")));
+    let mut section = cfg.fetch("section").unwrap();
+    assert!(section.replace_key("flag", Builder::from(false)));
+    let mut hosts = section.fetch("hosts").unwrap();
+    assert!(hosts.prepend(Builder::from("z")));
+    assert!(hosts.append(Builder::from("c")));
+    assert_eq!(hosts.delete_at(1).unwrap().as_string(), Some("a".to_string()));
+
+    assert_eq!(cfg.fetch("version").unwrap().as_int(), Some(2));
+    assert_eq!(cfg.fetch("name").unwrap().as_string(), Some("svc".to_string()));
+    assert_eq!(cfg.fetch_path("section.flag").unwrap().as_bool(), Some(false));
+    let hosts: Vec<_> = cfg.fetch_path("section.hosts").unwrap().iter()
+        .map(|h| h.as_string().unwrap())
+        .collect();
+    assert_eq!(hosts, vec!["z", "b", "c"]);
+}
+
+#[test]
+fn edit_delete() {
+    let mut cfg = ::Parser::new().parse("a = 1; a = 2; b = 3").unwrap();
+
+    assert!(cfg.delete_key("a"));
+    assert!(!cfg.delete_key("a"));
+    assert_eq!(cfg.keys().collect::<Vec<_>>(), vec!["b"]);
+}
+
+#[test]
+fn edit_through_aliases() {
+    let cfg = ::Parser::new().parse("a = null; b = 1; b = 2; c = 3").unwrap();
+    let mut root = cfg.new_ref();
+
+    let mut seen = Vec::new();
+    for (key, value) in cfg.entries(true) {
+        assert!(root.delete_key(&key) || key == "b");
+        seen.push((key, value.as_int()));
+    }
+    assert_eq!(seen, vec![
+        ("a".to_string(), None),
+        ("b".to_string(), Some(1)),
+        ("b".to_string(), Some(2)),
+        ("c".to_string(), Some(3)),
+    ]);
+    assert_eq!(cfg.keys().count(), 0);
+
+    let null = Builder::null().build();
+    let mut alias = null.new_ref();
+    assert!(alias.insert_key("x", Builder::from(1)));
+    assert_eq!(null.get_type(), Type::Object);
+    assert_eq!(null.fetch("x").unwrap().as_int(), Some(1));
+}
+
+#[test]
+fn edit_insert_shared() {
+    let mut cfg = ::Parser::new().parse("section { port = 1 }").unwrap();
+    let section = cfg.fetch("section").unwrap();
+    assert!(cfg.insert_key("copy", section));
+
+    let mut copy = cfg.fetch("copy").unwrap();
+    assert!(copy.replace_key("port", Builder::from(2)));
+    assert_eq!(cfg.fetch_path("section.port").unwrap().as_int(), Some(1));
+    assert_eq!(cfg.fetch_path("copy.port").unwrap().as_int(), Some(2));
+}
+
+#[test]
+fn edit_wrong_type() {
+    let mut obj = Builder::from(10).build();
+    assert!(!obj.insert_key("a", Builder::from(1)));
+    assert!(!obj.append(Builder::from(1)));
+    assert!(obj.delete_at(0).is_none());
+}
+
+#[test]
+fn build_nested() {
+    let obj = Builder::object()
+        .insert("name", "svc")
+        .insert("ports", Builder::array().push(80).push(443))
+        .insert("upstream", Builder::object().insert("host", "localhost"))
+        .build();
+
+    assert_eq!(obj.get_type(), Type::Object);
+    assert_eq!(obj.fetch_path("upstream.host").unwrap().as_string(), Some("localhost".to_string()));
+    assert_eq!(obj.dump_into(Emitter::JSONCompact),
+               r#"{"name":"svc","ports":[80,443],"upstream":{"host":"localhost"}}"#);
+}
+
+#[test]
+fn build_null_and_option() {
+    assert_eq!(Builder::null().build().get_type(), Type::Null);
+    assert_eq!(Builder::from(None::<i64>).build().get_type(), Type::Null);
+    assert_eq!(Builder::from(Some(10)).build().as_int(), Some(10));
+}
+
+#[test]
+fn build_from_collections() {
+    let arr = Builder::from(vec![1, 2, 3]).build();
+    assert_eq!(arr.get_type(), Type::Array);
+    assert_eq!(arr.size(), 3);
+
+    let mut map = HashMap::new();
+    map.insert("a".to_string(), vec!["x", "y"]);
+    let obj = Builder::from(map).build();
+    assert_eq!(obj.fetch("a").unwrap().at(1).unwrap().as_string(), Some("y".to_string()));
+
+    let mut map = BTreeMap::new();
+    map.insert("b", true);
+    map.insert("a", false);
+    let obj = Builder::from(map).build();
+    assert_eq!(obj.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+}
+
+#[test]
+fn build_from_object() {
+    let parsed = ::Parser::new().parse("section { port = 1 }").unwrap();
+    let obj = Builder::object()
+        .insert("copy", parsed.fetch("section").unwrap())
+        .build();
+
+    assert_eq!(obj.fetch_path("copy.port").unwrap().as_int(), Some(1));
+}
+
+#[test]
+fn emit_msgpack_binary() {
+    let obj = ::Parser::new().parse("a = 0; b = \"x\"").unwrap();
+    let bytes = obj.to_msgpack();
+
+    assert_eq!(bytes, vec![0x82, 0xa1, b'a', 0x00, 0xa1, b'b', 0xa1, b'x']);
+    assert_eq!(obj.emit_bytes(Emitter::MsgPack), bytes);
+    assert_eq!(Emitter::MsgPack.emit(&obj), None);
+}
+
+#[test]
+fn emit_bytes_text() {
+    let obj = ::Parser::new().parse("a { b = 1.0; c = [1, 2] }").unwrap();
+
+    assert_eq!(obj.emit_bytes(Emitter::JSONCompact), br#"{"a":{"b":1.0,"c":[1,2]}}"#.to_vec());
+    assert_eq!(obj.dump_into(Emitter::JSONCompact), r#"{"a":{"b":1.0,"c":[1,2]}}"#);
+    assert_eq!(obj.dump(), "{\n    \"a\": {\n        \"b\": 1.0,\n        \"c\": [\n            1,\n            2\n        ]\n    }\n}");
+}
+
+#[test]
+fn emit_non_finite() {
+    let obj = Builder::object()
+        .insert("nan", f64::NAN)
+        .insert("inf", f64::INFINITY)
+        .build();
+
+    assert_eq!(obj.dump_into(Emitter::JSONCompact), r#"{"nan":null,"inf":null}"#);
+    assert_eq!(EmitOptions::new(Emitter::JSONCompact).emit(&obj).unwrap(), r#"{"nan":null,"inf":null}"#);
+}
+
+/// Writer accepting only limited number of bytes.
+struct LimitedWriter {
+    data: Vec<u8>,
+    limit: usize,
+}
+
+impl Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.data.len() + buf.len() > self.limit {
+            return Err(io::Error::new(io::ErrorKind::WriteZero, "limit reached"))
+        }
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn write_to_writer() {
+    let obj = ::Parser::new().parse("a { b = 1.5; c = [1, 2] }; d = \"x\"").unwrap();
+
+    for emitter in [Emitter::JSON, Emitter::JSONCompact, Emitter::Config, Emitter::YAML, Emitter::MsgPack] {
+        let mut out = LimitedWriter { data: Vec::new(), limit: usize::MAX };
+        obj.write_to(&mut out, emitter).unwrap();
+        assert_eq!(out.data, obj.emit_bytes(emitter));
+    }
+}
+
+#[test]
+fn write_to_indentation() {
+    let obj = ::Parser::new().parse("a { b { c = 1 } }").unwrap();
+    let mut out = Vec::new();
+    obj.write_to(&mut out, Emitter::Config).unwrap();
+
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains("\n    b {\n        c = 1;\n    }\n"));
+}
+
+#[test]
+fn write_to_error() {
+    let obj = ::Parser::new().parse("a { b = 1.5; c = [1, 2] }; d = \"x\"").unwrap();
+    let mut out = LimitedWriter { data: Vec::new(), limit: 10 };
+
+    let err = obj.write_to(&mut out, Emitter::JSON).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+    assert!(out.data.len() <= 10);
+}
+
+#[test]
+#[should_panic(expected = "writer panicked")]
+fn write_to_panic() {
+    struct PanickingWriter;
+
+    impl Write for PanickingWriter {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            panic!("writer panicked")
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let obj = ::Parser::new().parse("a = 1").unwrap();
+    let _ = obj.write_to(PanickingWriter, Emitter::JSON);
+}
+
+fn stream_items() -> Vec<Object> {
+    vec![
+        Builder::from(1).build(),
+        Builder::object().insert("a", "b").insert("c", Builder::array().push(1.5)).build(),
+        Builder::from("x").build(),
+    ]
+}
+
+#[test]
+fn stream_array() {
+    let expected = stream_items().into_iter().fold(Builder::array(), |arr, item| arr.push(item)).build();
+
+    for emitter in [Emitter::JSON, Emitter::JSONCompact, Emitter::Config, Emitter::YAML] {
+        let mut stream = StreamEmitter::array(Vec::new(), emitter).unwrap();
+        for item in stream_items() {
+            stream.add(&item).unwrap();
+        }
+
+        assert_eq!(stream.finish().unwrap(), expected.emit_bytes(emitter));
+    }
+}
+
+#[test]
+fn stream_object() {
+    let doc = ::Parser::new().parse("section { a = 1 }").unwrap();
+    let expected = Builder::object()
+        .insert("first", 1)
+        .insert("second", Builder::array().push("x"))
+        .insert("section", doc.fetch("section").unwrap())
+        .build();
+
+    for emitter in [Emitter::JSON, Emitter::JSONCompact, Emitter::Config] {
+        let mut stream = StreamEmitter::object(Vec::new(), emitter).unwrap();
+        stream.add_entry("first", Builder::from(1)).unwrap();
+        stream.add_entry("second", Builder::array().push("x")).unwrap();
+        stream.add(&doc.fetch("section").unwrap()).unwrap();
+
+        assert_eq!(stream.finish().unwrap(), expected.emit_bytes(emitter));
+    }
+}
+
+#[test]
+fn stream_invalid_use() {
+    let err = StreamEmitter::array(Vec::new(), Emitter::MsgPack).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let mut stream = StreamEmitter::object(Vec::new(), Emitter::JSON).unwrap();
+    let err = stream.add(&Builder::from(1).build()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let mut stream = StreamEmitter::array(Vec::new(), Emitter::JSON).unwrap();
+    let err = stream.add_entry("a", Builder::from(1)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn stream_write_error() {
+    let out = LimitedWriter { data: Vec::new(), limit: 16 };
+    let mut stream = StreamEmitter::array(out, Emitter::JSONCompact).unwrap();
+
+    let item = Builder::from("0123456789").build();
+    assert!(stream.add(&item).is_ok());
+    assert_eq!(stream.add(&item).unwrap_err().kind(), io::ErrorKind::WriteZero);
+    assert!(stream.add(&item).is_err());
+    assert!(stream.finish().is_err());
+}
+
+#[test]
+fn format_config_defaults() {
+    let doc = ::Parser::new().parse(r#"
+        name = "svc";
+        "web root" = "/srv";
+        upstream { host = localhost; port = 80; tags = [a, b] }
+        hosts = 1;
+        hosts = 2;
+    "#).unwrap();
+
+    assert_eq!(EmitOptions::new(Emitter::Config).emit(&doc).unwrap(), r#"name = "svc";
+"web root" = "/srv";
+upstream {
+    host = "localhost";
+    port = 80;
+    tags [
+        "a",
+        "b",
+    ]
+}
+hosts = 1;
+hosts = 2;
+"#);
+}
+
+#[test]
+fn format_config_options() {
+    let doc = ::Parser::new().parse("b = true; a { y = 1.5; x = null; \"we\\\"ird\" = \"line\\nbreak\" }").unwrap();
+    let opts = EmitOptions::new(Emitter::Config)
+        .tabs(true)
+        .sort_keys(true)
+        .semicolons(false);
+
+    assert_eq!(opts.emit(&doc).unwrap(),
+               "a {\n\t\"we\\\"ird\" = \"line\\nbreak\"\n\tx = null\n\ty = 1.5\n}\nb = true\n");
+
+    let opts = opts.key_quoting(KeyQuoting::Always).separator(Separator::Colon);
+    assert_eq!(opts.emit(&doc).unwrap(),
+               "\"a\" {\n\t\"we\\\"ird\": \"line\\nbreak\"\n\t\"x\": null\n\t\"y\": 1.5\n}\n\"b\": true\n");
+}
+
+#[test]
+fn format_json() {
+    let doc = ::Parser::new().parse(r#"
+        a = [1, 2, 3];
+        b { c = ["long string value", "another long one"] }
+        d {}
+        h = 1;
+        h = 2;
+    "#).unwrap();
+
+    let opts = EmitOptions::new(Emitter::JSON).indent(2).array_width(20);
+    assert_eq!(opts.emit(&doc).unwrap(), r#"{
+  "a": [1, 2, 3],
+  "b": {
+    "c": [
+      "long string value",
+      "another long one"
+    ]
+  },
+  "d": {},
+  "h": [1, 2]
+}
+"#);
+
+    let opts = EmitOptions::new(Emitter::JSONCompact).indent(2).array_width(20);
+    assert_eq!(opts.emit(&doc).unwrap(),
+               r#"{"a":[1,2,3],"b":{"c":["long string value","another long one"]},"d":{},"h":[1,2]}"#);
+
+    // Repeated `h` is left as it was
+    let hs: Vec<_> = doc.entries(true).filter(|(key, _)| key == "h").map(|(_, v)| v.as_int()).collect();
+    assert_eq!(hs, vec![Some(1), Some(2)]);
+    assert!(opts.emit(&doc).unwrap().ends_with(r#""h":[1,2]}"#));
+}
+
+#[test]
+fn format_other_emitters() {
+    let doc = ::Parser::new().parse("a = 1").unwrap();
+    let opts = EmitOptions::new(Emitter::MsgPack).indent(2);
+
+    let mut out = Vec::new();
+    opts.write(&doc, &mut out).unwrap();
+    assert_eq!(out, doc.to_msgpack());
+    assert_eq!(opts.emit(&doc), None);
+}
+
+#[test]
+fn time_and_size_values() {
+    let doc = ::Parser::new().parse("a = 10s; b = 250ms; c = 2h; d = 10; e = 4mb; f = -1; g = 1.5kb").unwrap();
+
+    assert_eq!(doc.fetch("a").unwrap().as_duration(), Some(Duration::from_secs(10)));
+    assert_eq!(doc.fetch("b").unwrap().as_duration(), Some(Duration::from_millis(250)));
+    assert_eq!(doc.fetch("c").unwrap().as_duration(), Some(Duration::from_secs(7200)));
+    assert_eq!(doc.fetch("d").unwrap().as_duration(), None);
+
+    assert_eq!(doc.fetch("d").unwrap().as_bytes_size(), Some(10));
+    assert_eq!(doc.fetch("e").unwrap().as_bytes_size(), Some(4 << 20));
+    assert_eq!(doc.fetch("f").unwrap().as_bytes_size(), None);
+    assert_eq!(doc.fetch("g").unwrap().as_bytes_size(), Some(1536));
+    assert_eq!(doc.fetch("a").unwrap().as_bytes_size(), None);
+}
+
+#[test]
+fn build_and_emit_durations() {
+    let doc = Builder::object()
+        .insert("timeout", Duration::from_secs(300))
+        .insert("retry", Duration::from_millis(1500))
+        .insert("poll", Duration::from_millis(250))
+        .insert("ttl", Duration::from_secs(14 * 86400))
+        .build();
+    assert_eq!(doc.fetch("timeout").unwrap().get_type(), Type::Time);
+    assert_eq!(doc.fetch("retry").unwrap().as_duration(), Some(Duration::from_millis(1500)));
+
+    let opts = EmitOptions::new(Emitter::Config).human_durations(true);
+    let text = opts.emit(&doc).unwrap();
+    assert_eq!(text, "timeout = 5min;\nretry = 1500ms;\npoll = 250ms;\nttl = 2w;\n");
+
+    let parsed = ::Parser::new().parse(&text).unwrap();
+    assert_eq!(parsed.fetch("retry").unwrap().as_duration(), Some(Duration::from_millis(1500)));
+    assert_eq!(EmitOptions::new(Emitter::Config).emit(&doc).unwrap().lines().next(), Some("timeout = 300.0;"));
+}
+
+#[test]
+fn coercing_conversions() {
+    let doc = ::Parser::new().parse(r#"
+        port = " 8080 ";
+        size = "4kb";
+        big = 1e30;
+        ratio = 1;
+        flag = on;
+        quoted_flag = "On";
+        list = [1];
+    "#).unwrap();
+
+    assert_eq!(doc.fetch("port").unwrap().to_int_lossy(), Ok(8080));
+    assert_eq!(doc.fetch("size").unwrap().to_int_lossy(), Ok(4096));
+    assert_eq!(doc.fetch("ratio").unwrap().to_f64(), Ok(1.0));
+    assert_eq!(doc.fetch("flag").unwrap().to_bool(), Ok(true));
+    assert_eq!(doc.fetch("quoted_flag").unwrap().to_bool(), Ok(true));
+    assert_eq!(doc.fetch("ratio").unwrap().to_string_forced(), Ok("1".to_string()));
+
+    let err = doc.fetch("big").unwrap().to_int_lossy().unwrap_err();
+    assert_eq!(err.from_type(), Type::Float);
+    assert_eq!(err.target(), "i64");
+    assert_eq!(err.desc(), "value is out of range");
+
+    let err = doc.fetch("list").unwrap().to_string_forced().unwrap_err();
+    assert_eq!(err.to_string(), "cannot convert Array to String: value is not a scalar");
+    assert!(doc.fetch("list").unwrap().to_f64().is_err());
+    assert!(doc.fetch("port").unwrap().to_bool().is_err());
+}
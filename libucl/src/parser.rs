@@ -1,7 +1,20 @@
+use std::borrow::Cow;
 use std::ffi::CString;
+use std::io::Read;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::Path;
+#[cfg(unix)]
+use std::fs::File;
+#[cfg(unix)]
+use std::mem::ManuallyDrop;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, FromRawFd};
 
-use libc::size_t;
+use libc::{
+    c_uchar,
+    c_void,
+    size_t,
+};
 
 use error;
 use libucl_bind::*;
@@ -20,14 +33,139 @@ bitflags! {
         const ZEROCOPY           = 0x2;
         const NO_TIME            = 0x4;
         const NO_IMPLICIT_ARRAYS = 0x8;
+        const SAVE_COMMENTS      = 0x10;
+    }
+}
+
+/// Format of data passed to `Parser::parse_as`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseType {
+    /// UCL text, including plain JSON.
+    Ucl,
+    /// MessagePack, as produced by `Object::to_msgpack`.
+    Msgpack,
+    /// Canonical S-expressions. Lists become arrays and atoms become strings.
+    Sexp,
+    /// Detect format from the first byte of data. MessagePack is only recognised when it starts
+    /// with 16 or 32 bit array or map, so small documents should use `Msgpack` explicitly.
+    Auto,
+}
+
+impl From<ParseType> for ucl_parse_type {
+    fn from(parse_type: ParseType) -> Self {
+        match parse_type {
+            ParseType::Ucl => ucl_parse_type::UCL_PARSE_UCL,
+            ParseType::Msgpack => ucl_parse_type::UCL_PARSE_MSGPACK,
+            ParseType::Sexp => ucl_parse_type::UCL_PARSE_CSEXP,
+            ParseType::Auto => ucl_parse_type::UCL_PARSE_AUTO,
+        }
+    }
+}
+
+// Helper functions
+extern "C" fn call_macro(data: *const c_uchar, len: size_t, args: *const ucl_object_t, ud: *mut c_void) -> bool {
+    assert!(!ud.is_null());
+
+    let handler = unsafe { &mut *(ud as *mut MacroHandler) };
+    let body = if data.is_null() { &[][..] } else { unsafe { std::slice::from_raw_parts(data, len) } };
+    let args = Object::from_cptr(args);
+
+    // Unwinding into libucl is undefined behaviour, so panic is reported as rejected macro.
+    match panic::catch_unwind(AssertUnwindSafe(|| (handler.func)(body, args.as_ref()))) {
+        Ok(true) => true,
+        Ok(false) => {
+            handler.failure = Some(format!("macro `{}` rejected its input", handler.name));
+            false
+        },
+        Err(_) => {
+            handler.failure = Some(format!("macro `{}` panicked", handler.name));
+            false
+        }
+    }
+}
+
+extern "C" fn resolve_variable(data: *const c_uchar, len: size_t, replace: *mut *mut c_uchar,
+                                replace_len: *mut size_t, need_free: *mut bool, ud: *mut c_void) -> bool {
+    assert!(!data.is_null());
+    assert!(!ud.is_null());
+
+    let handler = unsafe { &mut *(ud as *mut VariablesHandler) };
+    let name = match std::str::from_utf8(unsafe { std::slice::from_raw_parts(data, len) }) {
+        Ok(name) => name,
+        Err(_) => return false
+    };
+    let value = match handler.func {
+        // Same as with macros, panic must not unwind into libucl; it fails parsing instead.
+        Some(ref func) => match panic::catch_unwind(AssertUnwindSafe(|| func(name))) {
+            Ok(value) => value,
+            Err(_) => {
+                if handler.failure.is_none() {
+                    handler.failure = Some(format!("variables handler panicked on `{}`", name));
+                }
+                return false
+            }
+        },
+        None => None
+    };
+
+    match value {
+        Some(value) => unsafe {
+            // libucl releases replacement with `free`, so it has to be allocated by `malloc`
+            let buf = libc::malloc(std::cmp::max(value.len(), 1)) as *mut c_uchar;
+            if buf.is_null() { return false }
+            std::ptr::copy_nonoverlapping(value.as_ptr(), buf, value.len());
+            *replace = buf;
+            *replace_len = value.len();
+            *need_free = true;
+            true
+        },
+        None => {
+            if handler.strict && !handler.undefined.iter().any(|n| n == name) {
+                handler.undefined.push(name.to_string());
+            }
+            false
+        }
     }
 }
 
-pub struct Parser {
+fn path_to_cstring(path: &Path) -> Result<CString> {
+    let invalid = || {
+        error::UclError::new(error::UclErrorType::Io, format!("invalid file name: {}", path.display()))
+    };
+    let name = path.to_str().ok_or_else(invalid)?;
+
+    CString::new(name).map_err(|_| invalid())
+}
+
+type VariablesFn<'a> = dyn Fn(&str) -> Option<Cow<'a, str>> + 'a;
+type MacroFn<'a> = dyn FnMut(&[u8], Option<&Object>) -> bool + 'a;
+
+struct VariablesHandler<'a> {
+    func: Option<Box<VariablesFn<'a>>>,
+    strict: bool,
+    undefined: Vec<String>,
+    failure: Option<String>,
+}
+
+struct MacroHandler<'a> {
+    name: String,
+    func: Box<MacroFn<'a>>,
+    failure: Option<String>,
+}
+
+/// UCL parser.
+///
+/// Lifetime `'a` bounds callbacks (like macro handlers) registered within parser.
+pub struct Parser<'a> {
     parser: *mut ucl_parser,
+    // Handlers are boxed, so pointers given to libucl stay valid when the vector grows
+    #[allow(clippy::vec_box)]
+    macros: Vec<Box<MacroHandler<'a>>>,
+    variables: Option<Box<VariablesHandler<'a>>>,
+    flags: Flags,
 }
 
-impl Parser {
+impl<'a> Parser<'a> {
     /// Create new parser instance with default options
     pub fn new() -> Self {
         Self::with_flags(Flags::DEFAULT)
@@ -43,6 +181,7 @@ impl Parser {
     ///   is not freed if an object is in use)
     /// - `NO_TIME` - do not parse time and treat it's value as string
     /// - `NO_IMPLICIT_ARRAYS` - create explicit arrays instead of implicit ones
+    /// - `SAVE_COMMENTS` - keep comments with parsed document, see `Object::dump_with_comments`
     ///
     /// # Examples
     ///
@@ -54,7 +193,10 @@ impl Parser {
     /// ```
     pub fn with_flags(flags: Flags) -> Self {
         Parser {
-            parser: unsafe { ucl_parser_new(flags.bits()) }
+            parser: unsafe { ucl_parser_new(flags.bits()) },
+            macros: Vec::new(),
+            variables: None,
+            flags,
         }
     }
 
@@ -68,30 +210,187 @@ impl Parser {
     /// assert!(libucl::Parser::new().parse("a = b").is_ok());
     /// assert!(libucl::Parser::new().parse("a =").is_err());
     /// ```
-    pub fn parse<T: AsRef<str>>(mut self, string: T) -> Result<Object> {
-        let len = string.as_ref().len() as size_t;
-        let s = CString::new(string.as_ref()).unwrap();
-        let result = unsafe { ucl_parser_add_chunk(self.parser, s.as_ptr(), len) };
-
-        if result {
-            Ok(self.get_object().unwrap())
-        } else {
-            Err(self.get_error())
-        }
+    pub fn parse<T: AsRef<str>>(self, string: T) -> Result<Object> {
+        self.add_chunk(string)?.finish()
+    }
+
+    /// Parse given bytes. Returns root object on success.
+    ///
+    /// Data is passed to libucl as is, without copying and without requiring it to be valid UTF-8
+    /// or free of NUL bytes; invalid input results in parse error. Data is only borrowed, as in
+    /// `ZEROCOPY` mode it must outlive returned object.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// assert!(libucl::Parser::new().parse_bytes(b"a = b").is_ok());
+    /// assert!(libucl::Parser::new().parse_bytes(b"a = \"b\0\"").is_err());
+    /// ```
+    pub fn parse_bytes(self, data: &[u8]) -> Result<Object> {
+        self.add_bytes(data)?.finish()
+    }
+
+    /// Parse given bytes in explicit format. Returns root object on success.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libucl::parser::ParseType;
+    ///
+    /// let blob = libucl::Parser::new().parse("a = 1").unwrap().to_msgpack();
+    /// let doc = libucl::Parser::new().parse_as(&blob, ParseType::Msgpack).unwrap();
+    ///
+    /// assert_eq!(doc.fetch("a").unwrap().as_int(), Some(1));
+    /// ```
+    pub fn parse_as(self, data: &[u8], parse_type: ParseType) -> Result<Object> {
+        self.add_bytes_as(data, parse_type)?.finish()
     }
 
     /// Parse file at given `Path`.
     ///
-    pub fn parse_file<T: AsRef<Path>>(mut self, path: T) -> Result<Object> {
-        let filename = path.as_ref().to_str().unwrap();
-        let s = CString::new(filename).unwrap();
+    pub fn parse_file<T: AsRef<Path>>(self, path: T) -> Result<Object> {
+        self.add_file(path)?.finish()
+    }
+
+    /// Parse everything that can be read from `reader`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let input = std::io::Cursor::new(b"a = b".to_vec());
+    /// let doc = libucl::Parser::new().parse_reader(input).unwrap();
+    ///
+    /// assert_eq!(doc.fetch("a").unwrap().as_string(), Some("b".to_string()));
+    /// ```
+    pub fn parse_reader<R: Read>(self, reader: R) -> Result<Object> {
+        self.add_reader(reader)?.finish()
+    }
+
+    /// Parse content of opened file descriptor. It can point to regular file as well as to pipe
+    /// or socket. Descriptor is not closed.
+    #[cfg(unix)]
+    pub fn parse_fd<F: AsRawFd>(self, fd: &F) -> Result<Object> {
+        self.add_fd(fd)?.finish()
+    }
+
+    /// Add string to parsed document.
+    ///
+    /// Unlike `parse` it allows to layer several sources within one document. Call `finish` to
+    /// obtain root object once all sources are added.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let doc = libucl::Parser::new()
+    ///     .add_chunk("a = 1; b = 1").unwrap()
+    ///     .add_chunk("c = 1").unwrap()
+    ///     .finish().unwrap();
+    ///
+    /// assert!(doc.fetch("a").is_some() && doc.fetch("c").is_some());
+    /// ```
+    pub fn add_chunk<T: AsRef<str>>(self, string: T) -> Result<Self> {
+        self.add_bytes(string.as_ref().as_bytes())
+    }
+
+    /// Add string with given priority to parsed document.
+    ///
+    /// Values from chunks with higher priority replace values with the same key from chunks with
+    /// lower priority. libucl supports priorities in range `0..=15`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let doc = libucl::Parser::new()
+    ///     .add_chunk_priority("port = 80; host = localhost", 0).unwrap()
+    ///     .add_chunk_priority("port = 8080", 5).unwrap()
+    ///     .finish().unwrap();
+    ///
+    /// assert_eq!(doc.fetch("port").unwrap().as_int(), Some(8080));
+    /// assert_eq!(doc.fetch("host").unwrap().as_string(), Some("localhost".to_string()));
+    /// ```
+    pub fn add_chunk_priority<T: AsRef<str>>(self, string: T, priority: u32) -> Result<Self> {
+        self.add_bytes_priority(string.as_ref().as_bytes(), priority)
+    }
+
+    /// Add bytes to parsed document.
+    ///
+    /// See `parse_bytes` for details.
+    pub fn add_bytes(mut self, data: &[u8]) -> Result<Self> {
+        self.add_data(data, None, ParseType::Ucl)?;
+        Ok(self)
+    }
+
+    /// Add bytes with given priority to parsed document.
+    ///
+    /// See `add_chunk_priority` for priorities description.
+    pub fn add_bytes_priority(mut self, data: &[u8], priority: u32) -> Result<Self> {
+        self.add_data(data, Some(priority), ParseType::Ucl)?;
+        Ok(self)
+    }
+
+    /// Add bytes in explicit format to parsed document.
+    ///
+    /// See `parse_as` for details.
+    pub fn add_bytes_as(mut self, data: &[u8], parse_type: ParseType) -> Result<Self> {
+        self.add_data(data, None, parse_type)?;
+        Ok(self)
+    }
+
+    /// Add file at given `Path` to parsed document.
+    pub fn add_file<T: AsRef<Path>>(mut self, path: T) -> Result<Self> {
+        let s = path_to_cstring(path.as_ref())?;
         let result = unsafe { ucl_parser_add_file(self.parser, s.as_ptr()) };
 
-        if result {
-            Ok(self.get_object().unwrap())
-        } else {
-            Err(self.get_error())
-        }
+        self.check_chunk(result, ucl_error_t::UCL_EIO)?;
+        Ok(self)
+    }
+
+    /// Add file at given `Path` with given priority to parsed document.
+    ///
+    /// See `add_chunk_priority` for priorities description.
+    pub fn add_file_priority<T: AsRef<Path>>(mut self, path: T, priority: u32) -> Result<Self> {
+        let s = path_to_cstring(path.as_ref())?;
+        let result = unsafe {
+            ucl_parser_add_file_priority(self.parser, s.as_ptr() as *const c_uchar, priority)
+        };
+
+        self.check_chunk(result, ucl_error_t::UCL_EIO)?;
+        Ok(self)
+    }
+
+    /// Add everything that can be read from `reader` to parsed document.
+    ///
+    /// Data is read into temporary buffer, so it can't be parsed by `ZEROCOPY` parser.
+    pub fn add_reader<R: Read>(mut self, reader: R) -> Result<Self> {
+        self.add_stream(reader, None)?;
+        Ok(self)
+    }
+
+    /// Add content of opened file descriptor to parsed document.
+    ///
+    /// Regular files are mapped into memory by libucl, other descriptors (pipes, sockets) are read
+    /// until end of stream, which `ZEROCOPY` parser refuses like `add_reader`. Descriptor is not
+    /// closed.
+    #[cfg(unix)]
+    pub fn add_fd<F: AsRawFd>(mut self, fd: &F) -> Result<Self> {
+        self.add_fd_data(fd, None)?;
+        Ok(self)
+    }
+
+    /// Add content of opened file descriptor with given priority to parsed document.
+    ///
+    /// See `add_chunk_priority` for priorities description.
+    #[cfg(unix)]
+    pub fn add_fd_priority<F: AsRawFd>(mut self, fd: &F, priority: u32) -> Result<Self> {
+        self.add_fd_data(fd, Some(priority))?;
+        Ok(self)
+    }
+
+    /// Finish parsing. Returns root object of document built from all added sources.
+    pub fn finish(mut self) -> Result<Object> {
+        self.get_object().ok_or_else(|| {
+            error::UclError::new(error::UclErrorType::State, "no data was added to parser".to_string())
+        })
     }
 
     /// Register new variable
@@ -113,19 +412,206 @@ impl Parser {
         }
     }
 
+    /// Set priority of chunks added without explicit priority, `0` by default.
+    ///
+    /// See `add_chunk_priority` for priorities description.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let p = libucl::Parser::new();
+    /// p.set_default_priority(2);
+    /// let doc = p.add_chunk("port = 80").unwrap()
+    ///     .add_chunk_priority("port = 8080", 1).unwrap()
+    ///     .finish().unwrap();
+    ///
+    /// assert_eq!(doc.fetch("port").unwrap().as_int(), Some(80));
+    /// ```
+    pub fn set_default_priority(&self, priority: u32) {
+        unsafe { ucl_parser_set_default_priority(self.parser, priority) };
+    }
+
+    /// Register handler for custom macro `.name`
+    ///
+    /// Handler receives macro body and its arguments (if any were given in parentheses) and
+    /// returns whether macro was accepted. Rejecting macro (or panicking within handler) fails
+    /// parsing with `UclErrorType::Macro` error. Names containing NUL bytes are refused.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut p = libucl::Parser::new();
+    /// p.register_macro("secret", |body, _args| body.starts_with(b"vault/")).unwrap();
+    ///
+    /// assert!(p.parse(r#".secret "vault/db"; a = b"#).is_ok());
+    /// ```
+    pub fn register_macro<F>(&mut self, name: &str, handler: F) -> Result<()>
+        where F: FnMut(&[u8], Option<&Object>) -> bool + 'a
+    {
+        let n = CString::new(name).map_err(|_| {
+            error::UclError::new(error::UclErrorType::Macro, format!("invalid macro name: {:?}", name))
+        })?;
+        let mut handler = Box::new(MacroHandler {
+            name: name.to_string(),
+            func: Box::new(handler),
+            failure: None,
+        });
+        let ud = &mut *handler as *mut MacroHandler as *mut c_void;
+        self.macros.push(handler);
+
+        unsafe {
+            ucl_parser_register_macro(self.parser, n.as_ptr(), call_macro, ud)
+        }
+        Ok(())
+    }
+
+    /// Set handler resolving variables that were not registered with `register_var`
+    ///
+    /// Handler is called with variable name and returns its value, or `None` when variable is
+    /// unknown, in which case variable is left unexpanded (see `deny_undefined_variables`).
+    /// libucl only consults handler for variables written as `${NAME}` and may ask for the same
+    /// variable more than once.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::borrow::Cow;
+    ///
+    /// let mut p = libucl::Parser::new();
+    /// p.set_variables_handler(|name| std::env::var(name).ok().map(Cow::Owned));
+    /// ```
+    pub fn set_variables_handler<F>(&mut self, handler: F)
+        where F: Fn(&str) -> Option<Cow<'a, str>> + 'a
+    {
+        self.variables_handler().func = Some(Box::new(handler));
+    }
+
+    /// Fail parsing when input references variable that cannot be resolved
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut p = libucl::Parser::new();
+    /// p.deny_undefined_variables(true);
+    ///
+    /// assert!(p.parse(r#"a = "${UNDEFINED}""#).is_err());
+    /// ```
+    pub fn deny_undefined_variables(&mut self, deny: bool) {
+        self.variables_handler().strict = deny;
+    }
+
+    fn variables_handler(&mut self) -> &mut VariablesHandler<'a> {
+        if self.variables.is_none() {
+            let mut handler = Box::new(VariablesHandler {
+                func: None,
+                strict: false,
+                undefined: Vec::new(),
+                failure: None,
+            });
+            let ud = &mut *handler as *mut VariablesHandler as *mut c_void;
+            unsafe { ucl_parser_set_variables_handler(self.parser, resolve_variable, ud) }
+            self.variables = Some(handler);
+        }
+
+        self.variables.as_mut().unwrap()
+    }
+
+    fn add_data(&mut self, data: &[u8], priority: Option<u32>, parse_type: ParseType) -> Result<()> {
+        let result = unsafe {
+            let priority = priority.unwrap_or_else(|| ucl_parser_get_default_priority(self.parser) as u32);
+            ucl_parser_add_chunk_full(self.parser, data.as_ptr(), data.len() as size_t, priority,
+                                      ucl_duplicate_strategy::UCL_DUPLICATE_APPEND, parse_type.into())
+        };
+
+        self.check_chunk(result, ucl_error_t::UCL_EINTERNAL)
+    }
+
+    #[cfg(unix)]
+    fn add_fd_data<F: AsRawFd>(&mut self, fd: &F, priority: Option<u32>) -> Result<()> {
+        // Borrow descriptor without taking ownership, so it is not closed on drop.
+        let file = ManuallyDrop::new(unsafe { File::from_raw_fd(fd.as_raw_fd()) });
+        let meta = file.metadata()?;
+
+        if !meta.is_file() || meta.len() == 0 {
+            return self.add_stream(&*file, priority)
+        }
+
+        let result = unsafe {
+            match priority {
+                Some(prio) => ucl_parser_add_fd_priority(self.parser, fd.as_raw_fd(), prio),
+                None => ucl_parser_add_fd(self.parser, fd.as_raw_fd())
+            }
+        };
+
+        self.check_chunk(result, ucl_error_t::UCL_EIO)
+    }
+
+    /// Add everything read from `reader`. Buffer is dropped right after parsing, so `ZEROCOPY`
+    /// parser, which keeps pointers into the input, is refused.
+    fn add_stream<R: Read>(&mut self, mut reader: R, priority: Option<u32>) -> Result<()> {
+        if self.flags.contains(Flags::ZEROCOPY) {
+            let desc = "ZEROCOPY parser can't read from stream".to_string();
+            return Err(error::UclError::new(error::UclErrorType::State, desc))
+        }
+
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        self.add_data(&data, priority, ParseType::Ucl)
+    }
+
+    /// Turn result of adding chunk into error, if any.
+    fn check_chunk(&mut self, result: bool, fallback: ucl_error_t) -> Result<()> {
+        if let Some(ref mut vars) = self.variables {
+            if let Some(desc) = vars.failure.take() {
+                vars.undefined.clear();
+                return Err(error::UclError::new(error::UclErrorType::Other, desc))
+            }
+        }
+
+        if !result {
+            return Err(self.get_error_or(fallback))
+        }
+
+        if let Some(ref mut vars) = self.variables {
+            if !vars.undefined.is_empty() {
+                let desc = format!("undefined variables: {}", vars.undefined.join(", "));
+                vars.undefined.clear();
+                return Err(error::UclError::new(error::UclErrorType::Other, desc))
+            }
+        }
+
+        Ok(())
+    }
+
     fn get_object(&mut self) -> Option<Object> {
-        object::Builder::from_ptr(unsafe { ucl_parser_get_object(self.parser) }).map(|o| o.build())
+        let obj = object::Builder::from_ptr(unsafe { ucl_parser_get_object(self.parser) })?.build();
+        let comments = unsafe { ucl_parser_get_comments(self.parser) };
+
+        Some(obj.with_comments(comments))
     }
 
-    fn get_error(&mut self) -> error::UclError {
-        let err = unsafe { ucl_parser_get_error_code(self.parser) };
+    /// Fetch last parser error. Some failures (e.g. missing files) do not set error code in
+    /// libucl, for those `fallback` is used.
+    fn get_error_or(&mut self, fallback: ucl_error_t) -> error::UclError {
+        if let Some(desc) = self.macros.iter_mut().filter_map(|m| m.failure.take()).next() {
+            return error::UclErrorType::from_code(ucl_error_t::UCL_EMACRO as i32, desc);
+        }
+
+        let mut err = unsafe { ucl_parser_get_error_code(self.parser) };
+        if err == ucl_error_t::UCL_EOK as i32 {
+            err = fallback as i32;
+        }
         let desc = unsafe { ucl_parser_get_error(self.parser) };
+        let file = unsafe { ucl_parser_get_cur_file(self.parser) };
+        let line = unsafe { ucl_parser_get_error_linenum(self.parser) };
+        let column = unsafe { ucl_parser_get_error_column(self.parser) };
 
-        error::UclErrorType::from_code(err, utils::to_str(desc).unwrap())
+        error::UclErrorType::from_code(err, utils::to_str(desc).unwrap_or_default())
+            .with_position(utils::to_str(file), line as usize, column as usize)
     }
 }
 
-impl Drop for Parser {
+impl<'a> Drop for Parser<'a> {
     fn drop(&mut self) {
         unsafe { ucl_parser_free(self.parser) }
     }
@@ -133,11 +619,10 @@ impl Drop for Parser {
 
 #[cfg(test)]
 mod test {
-    extern crate regex;
-    use self::regex::Regex;
+    use regex::Regex;
 
     use error::UclSchemaErrorType;
-    use object::Emitter;
+    use object::{Emitter, Type};
 
     use super::*;
 
@@ -157,6 +642,93 @@ mod test {
         assert!(p.parse(s).is_ok());
     }
 
+    #[test]
+    fn bytes_parsing() {
+        let res = Parser::new().parse_bytes(b"a = \"\xc5\x82\"; b = 1").unwrap();
+
+        assert_eq!(res.fetch("a").unwrap().as_string(), Some("ł".to_string()));
+        assert_eq!(res.fetch("b").unwrap().as_int(), Some(1));
+    }
+
+    #[test]
+    fn bytes_zerocopy() {
+        let data = b"a = value; b = [1, 2]".to_vec();
+        let res = Parser::with_flags(Flags::ZEROCOPY).parse_bytes(&data).unwrap();
+
+        assert_eq!(res.fetch("a").unwrap().as_string(), Some("value".to_string()));
+        assert_eq!(res.fetch("b").unwrap().size(), 2);
+        drop(res);
+        drop(data);
+    }
+
+    #[test]
+    fn non_utf8_parsing() {
+        let res = Parser::new().parse_bytes(&b"\"k\xff\" = \"v\xfe\""[..]).unwrap();
+        let (key, value) = res.entries(false).next().unwrap();
+
+        assert_eq!(key, "k\u{fffd}");
+        assert_eq!(value.key(), Some("k\u{fffd}".to_string()));
+        assert_eq!(value.as_string(), Some("v\u{fffd}".to_string()));
+    }
+
+    #[test]
+    fn nul_parsing() {
+        let err = Parser::new().parse("a = \"b\0c\"").unwrap_err();
+        assert_eq!(err.code(), error::UclErrorType::Syntax);
+
+        let err = Parser::new().parse_file("examples/te\0st.conf").unwrap_err();
+        assert_eq!(err.code(), error::UclErrorType::Io);
+    }
+
+    #[test]
+    fn comments_saving() {
+        let doc = r#"
+            # Main port
+            port = 80;
+            /* upstream section */
+            upstream {
+                # backend host
+                host = "localhost";
+            }
+        "#;
+        let mut res = Parser::with_flags(Flags::SAVE_COMMENTS).parse(doc).unwrap();
+        res.insert_key("workers", object::Builder::from(4));
+
+        let out = res.dump_with_comments(Emitter::Config);
+        assert!(out.contains("# Main port"));
+        assert!(out.contains("/* upstream section */"));
+        assert!(out.contains("# backend host"));
+        assert!(out.contains("workers = 4;"));
+        assert!(!res.dump_into(Emitter::Config).contains("# Main port"));
+
+        let res = Parser::new().parse(doc).unwrap();
+        assert!(!res.dump_with_comments(Emitter::Config).contains("# Main port"));
+    }
+
+    #[test]
+    fn msgpack_parsing() {
+        let obj = Parser::new().parse(r#"a = 1; b { c = [true, 1.5, "x"]; d = null }"#).unwrap();
+        let blob = obj.to_msgpack();
+
+        let res = Parser::new().parse_as(&blob, ParseType::Msgpack).unwrap();
+        assert_eq!(res.dump_into(Emitter::JSONCompact), obj.dump_into(Emitter::JSONCompact));
+
+        assert!(Parser::new().parse_as(b"\xc1", ParseType::Msgpack).is_err());
+    }
+
+    #[test]
+    fn sexp_parsing() {
+        let res = Parser::new().parse_as(b"(1:a2:bc)", ParseType::Sexp).unwrap();
+        assert_eq!(res.get_type(), Type::Array);
+        assert_eq!(res.at(1).unwrap().as_string(), Some("bc".to_string()));
+
+        let res = Parser::new().parse_as(b"(1:a)", ParseType::Auto).unwrap();
+        assert_eq!(res.get_type(), Type::Array);
+
+        let res = Parser::new().parse_as(b"a = b", ParseType::Auto).unwrap();
+        assert_eq!(res.fetch("a").unwrap().as_string(), Some("b".to_string()));
+    }
+
     #[test]
     fn key_fetching() {
         let p = Parser::new();
@@ -180,6 +752,27 @@ mod test {
         assert_eq!(res.fetch("test_var").unwrap().as_int(), Some(10));
     }
 
+    #[test]
+    fn error_position() {
+        let s = "a = b;\nc = d;\ne =";
+        let err = Parser::new().parse(s).unwrap_err();
+
+        assert_eq!(err.code(), error::UclErrorType::Syntax);
+        assert_eq!(err.line(), Some(3));
+        assert!(err.column().is_some());
+        assert_eq!(err.file(), None);
+        assert!(!err.desc().is_empty());
+    }
+
+    #[test]
+    fn error_file() {
+        let err = Parser::new().parse_file("examples/does_not_exist.conf").unwrap_err();
+
+        assert_eq!(err.code(), error::UclErrorType::Io);
+        assert_eq!(err.line(), None);
+        assert!(err.desc().contains("does_not_exist.conf"));
+    }
+
     #[test]
     fn variables() {
         let s = r#"testVar = $ENV"#;
@@ -190,6 +783,195 @@ mod test {
         assert_eq!(res.fetch("testVar").unwrap().as_string(), Some("test".to_string()));
     }
 
+    #[test]
+    fn chunks_priority() {
+        let res = Parser::new()
+            .add_chunk_priority("port = 80; host = localhost; flag = false", 1).unwrap()
+            .add_chunk_priority("port = 8080", 3).unwrap()
+            .add_chunk_priority("port = 8000; flag = true", 2).unwrap()
+            .finish().unwrap();
+
+        assert_eq!(res.fetch("port").unwrap().as_int(), Some(8080));
+        assert_eq!(res.fetch("host").unwrap().as_string(), Some("localhost".to_string()));
+        assert_eq!(res.fetch("flag").unwrap().as_bool(), Some(true));
+    }
+
+    #[test]
+    fn chunks_default_priority() {
+        let p = Parser::new();
+        p.set_default_priority(2);
+        let res = p
+            .add_chunk("port = 80; host = localhost").unwrap()
+            .add_chunk_priority("port = 8080; host = remote", 1).unwrap()
+            .add_chunk_priority("host = other", 3).unwrap()
+            .finish().unwrap();
+
+        assert_eq!(res.fetch("port").unwrap().as_int(), Some(80));
+        assert_eq!(res.fetch("host").unwrap().as_string(), Some("other".to_string()));
+    }
+
+    #[test]
+    fn chunks_file_and_fd() {
+        let file = std::fs::File::open("examples/test.conf").unwrap();
+        let res = Parser::new()
+            .add_file_priority("examples/test.conf", 0).unwrap()
+            .add_fd_priority(&file, 1).unwrap()
+            .add_chunk_priority("lol = override", 2).unwrap()
+            .finish().unwrap();
+
+        assert_eq!(res.fetch("lol").unwrap().as_string(), Some("override".to_string()));
+        assert_eq!(res.fetch_path("placki.duze").unwrap().as_bool(), Some(true));
+    }
+
+    #[test]
+    fn chunks_error() {
+        let err = Parser::new()
+            .add_chunk("a = b").unwrap()
+            .add_chunk("c =");
+
+        assert!(err.is_err());
+        assert_eq!(Parser::new().finish().unwrap_err().code(), error::UclErrorType::State);
+    }
+
+    #[test]
+    fn reader() {
+        let input = std::io::Cursor::new(b"a = 1; b { c = d }".to_vec());
+        let res = Parser::new().parse_reader(input).unwrap();
+
+        assert_eq!(res.fetch("a").unwrap().as_int(), Some(1));
+        assert_eq!(res.fetch_path("b.c").unwrap().as_string(), Some("d".to_string()));
+    }
+
+    #[test]
+    fn reader_error() {
+        struct Broken;
+
+        impl std::io::Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "broken"))
+            }
+        }
+
+        let err = Parser::new().parse_reader(Broken).unwrap_err();
+        assert_eq!(err.code(), error::UclErrorType::Io);
+        assert_eq!(err.desc(), "broken");
+    }
+
+    #[test]
+    fn reader_zerocopy() {
+        let input = std::io::Cursor::new(b"a = 1".to_vec());
+        let err = Parser::with_flags(Flags::ZEROCOPY).parse_reader(input).unwrap_err();
+        assert_eq!(err.code(), error::UclErrorType::State);
+
+        let (tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+        drop(tx);
+        assert!(Parser::with_flags(Flags::ZEROCOPY).parse_fd(&rx).is_err());
+    }
+
+    #[test]
+    fn fd_socket() {
+        use std::io::Write;
+        use std::os::unix::net::UnixStream;
+
+        let (mut tx, rx) = UnixStream::pair().unwrap();
+        tx.write_all(b"a = b").unwrap();
+        drop(tx);
+        let res = Parser::new().parse_fd(&rx).unwrap();
+
+        assert_eq!(res.fetch("a").unwrap().as_string(), Some("b".to_string()));
+    }
+
+    #[test]
+    fn fd_file() {
+        let file = std::fs::File::open("examples/test.conf").unwrap();
+        let res = Parser::new().parse_fd(&file).unwrap();
+
+        assert_eq!(res.fetch("lol").unwrap().as_string(), Some("lol".to_string()));
+    }
+
+    #[test]
+    fn variables_handler() {
+        use std::borrow::Cow;
+        use std::collections::HashMap;
+
+        let mut vars = HashMap::new();
+        vars.insert("HOME_DIR".to_string(), "/home/test".to_string());
+
+        let mut p = Parser::new();
+        p.set_variables_handler(|name| vars.get(name).map(|v| Cow::Borrowed(v.as_str())));
+        let res = p.parse(r#"path = "${HOME_DIR}/ucl"; other = "${UNKNOWN}""#).unwrap();
+
+        assert_eq!(res.fetch("path").unwrap().as_string(), Some("/home/test/ucl".to_string()));
+        assert!(res.fetch("other").is_some());
+    }
+
+    #[test]
+    fn undefined_variables() {
+        let mut p = Parser::new();
+        p.register_var("KNOWN".to_string(), "value".to_string());
+        p.deny_undefined_variables(true);
+        let err = p.parse(r#"a = "${KNOWN}"; b = "${MISSING}""#).unwrap_err();
+
+        assert_eq!(err.code(), error::UclErrorType::Other);
+        assert!(err.desc().contains("MISSING"));
+        assert!(!err.desc().contains("KNOWN"));
+    }
+
+    #[test]
+    fn variables_handler_panic() {
+        let mut p = Parser::new();
+        p.set_variables_handler(|name| if name == "BOOM" { panic!("boom") } else { None });
+        let err = p.parse(r#"a = "${BOOM}""#).unwrap_err();
+
+        assert_eq!(err.code(), error::UclErrorType::Other);
+        assert!(err.desc().contains("BOOM"));
+    }
+
+    #[test]
+    fn macros() {
+        use std::cell::RefCell;
+
+        let secrets = RefCell::new(Vec::new());
+        let mut p = Parser::new();
+        p.register_macro("secret", |body, args| {
+            let key = args.and_then(|a| a.fetch("key")).and_then(|k| k.as_string());
+            secrets.borrow_mut().push((body.to_vec(), key));
+            true
+        }).unwrap();
+        let res = p.parse(r#".secret(key = "db") "vault/path"
+            name = test"#).unwrap();
+
+        assert_eq!(res.fetch("name").unwrap().as_string(), Some("test".to_string()));
+        assert_eq!(*secrets.borrow(), vec![(b"vault/path".to_vec(), Some("db".to_string()))]);
+    }
+
+    #[test]
+    fn macro_rejected() {
+        let mut p = Parser::new();
+        p.register_macro("feature_flag", |_, _| false).unwrap();
+        let err = p.parse(r#".feature_flag "beta""#).unwrap_err();
+
+        assert_eq!(err.code(), error::UclErrorType::Macro);
+        assert!(err.desc().contains("feature_flag"));
+    }
+
+    #[test]
+    fn macro_panic() {
+        let mut p = Parser::new();
+        p.register_macro("boom", |_, _| panic!("boom")).unwrap();
+
+        assert_eq!(p.parse(r#".boom "x""#).unwrap_err().code(), error::UclErrorType::Macro);
+    }
+
+    #[test]
+    fn macro_invalid_name() {
+        let mut p = Parser::new();
+        let err = p.register_macro("bad\0name", |_, _| true).unwrap_err();
+
+        assert_eq!(err.code(), error::UclErrorType::Macro);
+        assert!(p.parse("a = b").is_ok());
+    }
+
     #[test]
     fn parse_array_and_iter() {
         let parser = Parser::new();
@@ -202,14 +984,16 @@ mod test {
         let val = result.fetch_path("section.server");
         assert!(val.is_some());
 
-        let mut obj = val.unwrap();
+        let obj = val.unwrap();
         assert_eq!(obj.get_type() == object::Type::Array, true);
-        assert_eq!(obj.next().unwrap().as_string().unwrap(), "http://localhost:6666");
-        assert_eq!(obj.next().unwrap().as_string().unwrap(), "test_string");
-        assert_eq!(obj.next().is_none(), true);
+        let mut it = obj.iter();
+        assert_eq!(it.len(), 2);
+        assert_eq!(it.next().unwrap().as_string().unwrap(), "http://localhost:6666");
+        assert_eq!(it.next().unwrap().as_string().unwrap(), "test_string");
+        assert!(it.next().is_none());
 
         let val = result.fetch_path("section.server").unwrap();
-        for o in val {
+        for o in &val {
             assert_ne!(o.as_string(), None);
         }
 
@@ -226,7 +1010,8 @@ mod test {
             }"#).unwrap();
         let val = result.fetch_path("section.server");
         assert!(val.is_some());
-        assert_eq!(result.dump().len(), 138);
+        // Nested elements are indented by 4 spaces per level
+        assert_eq!(result.dump().len(), 197);
     }
 
     #[test]
@@ -400,4 +1185,51 @@ mod test {
         assert_eq!(res.is_err(), true);
         assert_eq!(res.err().unwrap().code, UclSchemaErrorType::MissingDependency)
     }
+
+    #[test]
+    fn validate_with_schema_error_details() {
+        let parser = Parser::new();
+        let item = r#"section { hosts [ { port = 80 }, { port = "http" } ] }"#;
+        let schema = r#"{"type": "object", "properties": {"section": {"type": "object", "properties": {
+            "hosts": {"type": "array", "items": {"type": "object", "properties": {"port": {"type": "integer"}}}}
+        }}}}"#;
+        let item = parser.parse(item).unwrap();
+        let parser = Parser::new();
+        let schema = parser.parse(schema).unwrap();
+        let err = item.validate_with_schema(&schema).err().unwrap();
+        assert_eq!(err.code, UclSchemaErrorType::TypeMismatch);
+        assert!(!err.desc.is_empty());
+        assert_eq!(err.path, Some("section.hosts[1].port".to_string()));
+        assert_eq!(err.element(&item).unwrap().as_string(), Some("http".to_string()));
+    }
+
+    #[test]
+    fn validate_with_schema_error_repeated_key() {
+        let parser = Parser::new();
+        let item = parser.parse(r#"hosts { port = 80 } hosts { port = "http" }"#).unwrap();
+        let parser = Parser::new();
+        let schema = parser.parse(r#"{"type": "object", "properties": {
+            "hosts": {"type": "object", "properties": {"port": {"type": "integer"}}}
+        }}"#).unwrap();
+        let err = item.validate_with_schema(&schema).err().unwrap();
+        assert_eq!(err.path, Some("hosts[1].port".to_string()));
+        assert_eq!(err.element(&item).unwrap().as_string(), Some("http".to_string()));
+        assert_eq!(item.find_path("hosts[0].port").unwrap().as_int(), Some(80));
+
+        // Lookups leave repeated values in place
+        let ports: Vec<_> = item.entries(true).map(|(_, v)| v.fetch("port").unwrap().get_type()).collect();
+        assert_eq!(ports, vec![Type::Int, Type::String]);
+    }
+
+    #[test]
+    fn validate_with_schema_error_root() {
+        let parser = Parser::new();
+        let item = parser.parse(r#"{"key": "value"}"#).unwrap();
+        let parser = Parser::new();
+        let schema = parser.parse(r#"{"type": "object", "required": ["other"]}"#).unwrap();
+        let err = item.validate_with_schema(&schema).err().unwrap();
+        assert_eq!(err.code, UclSchemaErrorType::MissingProperty);
+        assert_eq!(err.path, Some(String::new()));
+        assert_eq!(err.to_string(), err.desc);
+    }
 }
@@ -0,0 +1,927 @@
+//! Validation schemas.
+//!
+//! `Schema` checks a JSON schema (draft v4) once when it is loaded and then validates any number
+//! of documents against it. External documents referenced from the schema, like
+//! `{"$ref": "common.json#/definitions/port"}`, are read and checked at load time too, instead of
+//! on every validation.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use libucl::{Parser, Schema};
+//!
+//! let schema: Schema = r#"{"type": "object", "properties": {"port": {"type": "integer"}}}"#.parse().unwrap();
+//!
+//! let good = Parser::new().parse("port = 80").unwrap();
+//! let bad = Parser::new().parse("port = http").unwrap();
+//! assert!(schema.validate(&good).is_ok());
+//! assert_eq!(schema.validate(&bad).unwrap_err().path, Some("port".to_string()));
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use regex::Regex;
+
+use error::{UclError, UclErrorType, UclSchemaError, UclSchemaErrorType};
+use object::{Builder, Object, Type};
+use parser::Parser;
+
+const TYPES: &[&str] = &["object", "array", "integer", "number", "string", "boolean", "null"];
+
+/// Limit of nested `$ref` lookups in `Schema::validate_all`, guards against reference cycles.
+const MAX_REF_DEPTH: usize = 256;
+
+/// JSON schema checked and ready for validation.
+pub struct Schema {
+    root: Object,
+    ext_refs: Object,
+    /// `pattern` and `patternProperties` expressions, compiled once by `check`.
+    patterns: HashMap<String, Regex>,
+}
+
+impl Schema {
+    /// Load schema from object. External references are read from files relative to the current
+    /// directory.
+    pub fn from_object(root: Object) -> Result<Self, UclSchemaError> {
+        Schema::with_resolver(root, |uri| read_file(None, uri))
+    }
+
+    /// Parse and load schema from file. External references are read from files relative to the
+    /// directory of the schema.
+    pub fn from_file<T: AsRef<Path>>(path: T) -> Result<Self, UclSchemaError> {
+        let path = path.as_ref();
+        let root = Parser::new().parse_file(path).map_err(|err| load_error(err, None))?;
+        let base = path.parent().map(Path::to_path_buf);
+
+        Schema::with_resolver(root, |uri| read_file(base.as_ref(), uri))
+    }
+
+    /// Load schema from object, resolving external references with `resolver`.
+    ///
+    /// `resolver` is called once for every distinct document referenced from the schema, or from
+    /// already resolved documents, with the part of `$ref` preceding `#`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libucl::{Parser, Schema};
+    ///
+    /// let root = Parser::new().parse(r#"{"properties": {"port": {"$ref": "port.json"}}}"#).unwrap();
+    /// let schema = Schema::with_resolver(root, |uri| {
+    ///     assert_eq!(uri, "port.json");
+    ///     Parser::new().parse(r#"{"type": "integer", "maximum": 65535}"#)
+    /// }).unwrap();
+    ///
+    /// assert!(schema.validate(&Parser::new().parse("port = 8080").unwrap()).is_ok());
+    /// assert!(schema.validate(&Parser::new().parse("port = 80000").unwrap()).is_err());
+    /// ```
+    pub fn with_resolver<F>(root: Object, mut resolver: F) -> Result<Self, UclSchemaError>
+        where F: FnMut(&str) -> Result<Object, UclError>
+    {
+        let mut refs = Vec::new();
+        let mut patterns = HashMap::new();
+        check(&root, "", &mut refs, &mut patterns)?;
+
+        let mut ext_refs = Builder::object().build();
+        while let Some((uri, path)) = refs.pop() {
+            if ext_refs.fetch(&uri).is_some() {
+                continue
+            }
+            let doc = resolver(&uri).map_err(|err| {
+                let mut error = load_error(err, Some(path));
+                error.desc = format!("cannot resolve reference `{}`: {}", uri, error.desc);
+                error
+            })?;
+            check(&doc, "", &mut refs, &mut patterns).map_err(|mut err| {
+                err.path = err.path.map(|path| format!("{}#{}", uri, path));
+                err
+            })?;
+            ext_refs.insert_key(uri, doc);
+        }
+
+        Ok(Schema { root, ext_refs, patterns })
+    }
+
+    /// Validate `obj` against schema.
+    pub fn validate(&self, obj: &Object) -> Result<(), UclSchemaError> {
+        obj.validate_ext(&self.root, Some(&self.ext_refs))
+    }
+
+    /// Validate `obj` against schema, reporting every violation instead of stopping at the first
+    /// one. Returns empty vector for valid documents.
+    ///
+    /// Documents are walked in Rust rather than by libucl, with repeated keys checked as arrays.
+    /// Patterns are matched with the `regex` crate, whose syntax is closer to ECMA 262 expected by
+    /// JSON schema than POSIX expressions of libucl, so `\d` and alike work here.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libucl::{Parser, Schema};
+    ///
+    /// let schema: Schema = r#"{"properties": {"port": {"type": "integer"}, "host": {"type": "string"}}}"#.parse().unwrap();
+    /// let doc = Parser::new().parse("port = http; host = 1").unwrap();
+    ///
+    /// let paths: Vec<_> = schema.validate_all(&doc).into_iter().filter_map(|err| err.path).collect();
+    /// assert_eq!(paths, vec!["port", "host"]);
+    /// ```
+    pub fn validate_all(&self, obj: &Object) -> Vec<UclSchemaError> {
+        let mut errors = Vec::new();
+        self.walk(&self.root, &self.root, obj, "", 0, &mut errors);
+        errors
+    }
+
+    /// Load schema generated for type `T`.
+    pub fn of<T: UclSchema + ?Sized>() -> Result<Self, UclSchemaError> {
+        Schema::from_object(T::ucl_schema().build())
+    }
+
+    /// Root of the schema.
+    pub fn root(&self) -> &Object {
+        &self.root
+    }
+
+    /// Check `value` against `schema` taken from document `root`.
+    fn walk(&self, root: &Object, schema: &Object, value: &Object, path: &str, depth: usize, errors: &mut Vec<UclSchemaError>) {
+        if let Some(reference) = schema.fetch("$ref").and_then(|r| r.as_string()) {
+            if depth >= MAX_REF_DEPTH {
+                let desc = format!("reference `{}` is nested too deep", reference);
+                return errors.push(violation(UclSchemaErrorType::InvalidSchema, path, desc))
+            }
+            match self.resolve(root, &reference) {
+                Some((root, target)) => self.walk(&root, &target, value, path, depth + 1, errors),
+                None => {
+                    let desc = format!("cannot resolve reference `{}`", reference);
+                    errors.push(violation(UclSchemaErrorType::InvalidSchema, path, desc))
+                }
+            }
+            return
+        }
+
+        let typ = value.get_type();
+        if let Some(types) = schema.fetch("type") {
+            let names: Vec<_> = if types.get_type() == Type::Array {
+                types.iter().filter_map(|name| name.as_string()).collect()
+            } else {
+                types.as_string().into_iter().collect()
+            };
+            if !names.iter().any(|name| type_allows(name, typ)) {
+                let desc = format!("Invalid type of {}, expected {}", type_name(typ), names.join(" or "));
+                return errors.push(violation(UclSchemaErrorType::TypeMismatch, path, desc))
+            }
+        }
+
+        if let Some(values) = schema.fetch("enum") {
+            if !values.iter().any(|v| v.same_value(value)) {
+                let desc = "value does not match any of enum values".to_string();
+                errors.push(violation(UclSchemaErrorType::Constraint, path, desc));
+            }
+        }
+        if let Some(subs) = schema.fetch("allOf") {
+            for sub in subs.iter() {
+                self.walk(root, &sub, value, path, depth, errors);
+            }
+        }
+        if let Some(subs) = schema.fetch("anyOf") {
+            if !subs.iter().any(|sub| self.passes(root, &sub, value, depth)) {
+                let desc = "value does not match any schema of anyOf".to_string();
+                errors.push(violation(UclSchemaErrorType::Constraint, path, desc));
+            }
+        }
+        if let Some(subs) = schema.fetch("oneOf") {
+            let matched = subs.iter().filter(|sub| self.passes(root, sub, value, depth)).count();
+            if matched != 1 {
+                let desc = format!("value matches {} schemas of oneOf, expected exactly one", matched);
+                errors.push(violation(UclSchemaErrorType::Constraint, path, desc));
+            }
+        }
+        if let Some(sub) = schema.fetch("not") {
+            if self.passes(root, &sub, value, depth) {
+                let desc = "value matches schema of not".to_string();
+                errors.push(violation(UclSchemaErrorType::Constraint, path, desc));
+            }
+        }
+
+        match typ {
+            Type::Object => self.walk_object(root, schema, value, path, depth, errors),
+            Type::Array => self.walk_array(root, schema, value, path, depth, errors),
+            Type::String => self.walk_string(schema, value, path, errors),
+            Type::Int | Type::Float => walk_number(schema, value, path, errors),
+            _ => {}
+        }
+    }
+
+    fn passes(&self, root: &Object, schema: &Object, value: &Object, depth: usize) -> bool {
+        let mut errors = Vec::new();
+        self.walk(root, schema, value, "", depth, &mut errors);
+        errors.is_empty()
+    }
+
+    fn walk_string(&self, schema: &Object, value: &Object, path: &str, errors: &mut Vec<UclSchemaError>) {
+        let string = value.as_string().unwrap_or_default();
+
+        let limits = bounds(schema, "minLength", "maxLength");
+        check_count(limits, string.chars().count(), "string is too short", "string is too long", path, errors);
+
+        if let Some(pattern) = schema.fetch("pattern").and_then(|p| p.as_string()) {
+            if self.matches(&pattern, &string, path, errors) == Some(false) {
+                let desc = format!("string doesn't match regex {}", pattern);
+                errors.push(violation(UclSchemaErrorType::Constraint, path, desc));
+            }
+        }
+    }
+
+    /// Whether `pattern` matches anywhere in `string`, `None` (with error recorded) for invalid
+    /// pattern. Patterns outside of parts checked on load, only reachable by `$ref` pointing into
+    /// unknown keywords, are compiled on each use.
+    fn matches(&self, pattern: &str, string: &str, path: &str, errors: &mut Vec<UclSchemaError>) -> Option<bool> {
+        if let Some(regex) = self.patterns.get(pattern) {
+            return Some(regex.is_match(string))
+        }
+
+        match Regex::new(pattern) {
+            Ok(regex) => Some(regex.is_match(string)),
+            Err(_) => {
+                errors.push(invalid(path, format!("`{}` is not a valid regular expression", pattern)));
+                None
+            }
+        }
+    }
+
+    fn walk_object(&self, root: &Object, schema: &Object, value: &Object, path: &str, depth: usize, errors: &mut Vec<UclSchemaError>) {
+        let properties = schema.fetch("properties");
+        let patterns = schema.fetch("patternProperties");
+        let additional = schema.fetch("additionalProperties");
+
+        // Values of repeated key are checked as array of their copies, paths still lead to the
+        // values themselves
+        let mut count = 0;
+        for (key, elt) in value.entries(false) {
+            count += 1;
+            let elt_path = child(path, &key);
+            let mut matched = false;
+
+            if let Some(sub) = properties.as_ref().and_then(|props| props.fetch(&key)) {
+                matched = true;
+                self.walk(root, &sub, &elt, &elt_path, depth, errors);
+            }
+            if let Some(ref patterns) = patterns {
+                for (pattern, sub) in patterns.entries(true) {
+                    if self.matches(&pattern, &key, path, errors) == Some(true) {
+                        matched = true;
+                        self.walk(root, &sub, &elt, &elt_path, depth, errors);
+                    }
+                }
+            }
+            match additional {
+                Some(ref additional) if !matched => match additional.as_bool() {
+                    Some(false) => {
+                        let desc = format!("object has non-allowed property {}", key);
+                        errors.push(violation(UclSchemaErrorType::Constraint, &elt_path, desc));
+                    },
+                    Some(true) => {},
+                    None => self.walk(root, additional, &elt, &elt_path, depth, errors),
+                },
+                _ => {}
+            }
+        }
+
+        if let Some(required) = schema.fetch("required") {
+            for name in required.iter().filter_map(|name| name.as_string()) {
+                if value.fetch(&name).is_none() {
+                    let desc = format!("object has missing property {}", name);
+                    errors.push(violation(UclSchemaErrorType::MissingProperty, path, desc));
+                }
+            }
+        }
+        if let Some(deps) = schema.fetch("dependencies") {
+            for (name, dep) in deps.entries(true) {
+                if value.fetch(&name).is_none() {
+                    continue
+                }
+                if dep.get_type() != Type::Array {
+                    self.walk(root, &dep, value, path, depth, errors);
+                    continue
+                }
+                for missing in dep.iter().filter_map(|d| d.as_string()).filter(|d| value.fetch(d).is_none()) {
+                    let desc = format!("dependency for {} is not satisfied: {} is missing", name, missing);
+                    errors.push(violation(UclSchemaErrorType::MissingDependency, path, desc));
+                }
+            }
+        }
+
+        let limits = bounds(schema, "minProperties", "maxProperties");
+        check_count(limits, count, "object has too few properties", "object has too many properties", path, errors);
+    }
+
+    fn walk_array(&self, root: &Object, schema: &Object, value: &Object, path: &str, depth: usize, errors: &mut Vec<UclSchemaError>) {
+        let items: Vec<_> = value.iter().collect();
+
+        if let Some(item_schema) = schema.fetch("items") {
+            let additional = schema.fetch("additionalItems");
+            for (i, elt) in items.iter().enumerate() {
+                let elt_path = format!("{}[{}]", path, i);
+                if item_schema.get_type() != Type::Array {
+                    self.walk(root, &item_schema, elt, &elt_path, depth, errors);
+                } else if let Some(sub) = item_schema.at(i) {
+                    self.walk(root, &sub, elt, &elt_path, depth, errors);
+                } else if let Some(ref additional) = additional {
+                    match additional.as_bool() {
+                        Some(false) => {
+                            let desc = format!("array has undefined item {}", i);
+                            errors.push(violation(UclSchemaErrorType::Constraint, &elt_path, desc));
+                        },
+                        Some(true) => {},
+                        None => self.walk(root, additional, elt, &elt_path, depth, errors),
+                    }
+                }
+            }
+        }
+
+        if schema.fetch("uniqueItems").and_then(|u| u.as_bool()) == Some(true) {
+            for (i, elt) in items.iter().enumerate() {
+                if items[..i].iter().any(|prev| prev.same_value(elt)) {
+                    let desc = format!("array has duplicate item {}", i);
+                    errors.push(violation(UclSchemaErrorType::Constraint, &format!("{}[{}]", path, i), desc));
+                }
+            }
+        }
+
+        let limits = bounds(schema, "minItems", "maxItems");
+        check_count(limits, items.len(), "array has too few items", "array has too many items", path, errors);
+    }
+
+    /// Find target of `$ref` together with root of the document containing it.
+    fn resolve(&self, root: &Object, reference: &str) -> Option<(Object, Object)> {
+        let (doc, pointer) = split_ref(reference);
+
+        let doc_root = if doc.is_empty() { root.new_ref() } else { self.ext_refs.fetch(doc)? };
+        let mut target = doc_root.new_ref();
+        for token in pointer.split('/').filter(|token| !token.is_empty()) {
+            let token = token.replace("~1", "/").replace("~0", "~");
+            target = if target.get_type() == Type::Array {
+                target.at(token.parse().ok()?)?
+            } else {
+                target.fetch(&token)?
+            };
+        }
+        Some((doc_root, target))
+    }
+}
+
+/// Parse and load schema from string. External references are read from files relative to the
+/// current directory.
+impl FromStr for Schema {
+    type Err = UclSchemaError;
+
+    fn from_str(string: &str) -> Result<Self, UclSchemaError> {
+        let root = Parser::new().parse(string).map_err(|err| load_error(err, None))?;
+        Schema::from_object(root)
+    }
+}
+
+fn walk_number(schema: &Object, value: &Object, path: &str, errors: &mut Vec<UclSchemaError>) {
+    let n = number(value).unwrap_or_default();
+    let exclusive = |key| schema.fetch(key).and_then(|e| e.as_bool()).unwrap_or(false);
+
+    if let Some(max) = schema.fetch("maximum").as_ref().and_then(number) {
+        if n > max || (n == max && exclusive("exclusiveMaximum")) {
+            let desc = format!("number is too big: {}, maximum is: {}", n, max);
+            errors.push(violation(UclSchemaErrorType::Constraint, path, desc));
+        }
+    }
+    if let Some(min) = schema.fetch("minimum").as_ref().and_then(number) {
+        if n < min || (n == min && exclusive("exclusiveMinimum")) {
+            let desc = format!("number is too small: {}, minimum is: {}", n, min);
+            errors.push(violation(UclSchemaErrorType::Constraint, path, desc));
+        }
+    }
+    if let Some(step) = schema.fetch("multipleOf").as_ref().and_then(number) {
+        let quotient = n / step;
+        if (quotient - quotient.round()).abs() > 1e-9 {
+            let desc = format!("number {} is not a multiple of {}", n, step);
+            errors.push(violation(UclSchemaErrorType::Constraint, path, desc));
+        }
+    }
+}
+
+fn number(obj: &Object) -> Option<f64> {
+    obj.as_int().map(|n| n as f64).or_else(|| obj.as_float())
+}
+
+/// Values of pair of minimum and maximum count keywords.
+fn bounds(schema: &Object, min: &str, max: &str) -> (Option<i64>, Option<i64>) {
+    let get = |key| schema.fetch(key).and_then(|v| v.as_int());
+    (get(min), get(max))
+}
+
+fn check_count(limits: (Option<i64>, Option<i64>), count: usize, few: &str, many: &str,
+               path: &str, errors: &mut Vec<UclSchemaError>) {
+    let count = count as i64;
+    if let Some(min) = limits.0.filter(|&min| count < min) {
+        let desc = format!("{}: {}, minimum is: {}", few, count, min);
+        errors.push(violation(UclSchemaErrorType::Constraint, path, desc));
+    }
+    if let Some(max) = limits.1.filter(|&max| count > max) {
+        let desc = format!("{}: {}, maximum is: {}", many, count, max);
+        errors.push(violation(UclSchemaErrorType::Constraint, path, desc));
+    }
+}
+
+fn type_allows(name: &str, typ: Type) -> bool {
+    matches!((name, typ),
+        ("object", Type::Object) | ("array", Type::Array) | ("string", Type::String) |
+        ("integer", Type::Int) | ("number", Type::Int) | ("number", Type::Float) |
+        ("boolean", Type::Boolean) | ("null", Type::Null))
+}
+
+fn type_name(typ: Type) -> &'static str {
+    match typ {
+        Type::Object => "object",
+        Type::Array => "array",
+        Type::Int => "integer",
+        Type::Float => "number",
+        Type::String => "string",
+        Type::Boolean => "boolean",
+        Type::Time => "time",
+        Type::UserData => "userdata",
+        Type::Null => "null",
+    }
+}
+
+/// Compile `pattern` into `patterns` unless already there, `false` if it is not valid.
+fn compile(pattern: &str, patterns: &mut HashMap<String, Regex>) -> bool {
+    if patterns.contains_key(pattern) {
+        return true
+    }
+
+    match Regex::new(pattern) {
+        Ok(regex) => {
+            patterns.insert(pattern.to_string(), regex);
+            true
+        },
+        Err(_) => false
+    }
+}
+
+/// Types describing their UCL representation, as read by `from_object`, with JSON schema.
+///
+/// Implement it with `#[derive(UclSchema)]` under the `derive` feature. Doc comments of
+/// structs, fields and variants become descriptions.
+///
+/// # Examples
+///
+/// ```rust
+/// use libucl::schema::UclSchema;
+/// use libucl::{Parser, Schema};
+///
+/// let schema = Schema::of::<Vec<Option<u16>>>().unwrap();
+///
+/// assert!(schema.validate(&Parser::new().parse("[80, null, 443]").unwrap()).is_ok());
+/// assert!(schema.validate(&Parser::new().parse("[-1]").unwrap()).is_err());
+/// assert_eq!(<u16>::ucl_schema().build().fetch("maximum").unwrap().as_int(), Some(65535));
+/// ```
+pub trait UclSchema {
+    /// Schema of the type.
+    fn ucl_schema() -> Builder;
+
+    /// Whether field of this type may be left out of object, `true` for `Option`.
+    fn ucl_optional() -> bool {
+        false
+    }
+}
+
+/// Replace description of `schema`, used by `#[derive(UclSchema)]`.
+#[doc(hidden)]
+pub fn describe(schema: Builder, desc: &str) -> Builder {
+    let mut schema = schema.build();
+    schema.replace_key("description", Builder::from(desc));
+    Builder::from(schema)
+}
+
+fn typed(name: &str) -> Builder {
+    Builder::object().insert("type", name)
+}
+
+macro_rules! schema_int {
+    ($($ty: ty),*) => {$(
+        impl UclSchema for $ty {
+            fn ucl_schema() -> Builder {
+                typed("integer")
+                    .insert("minimum", <$ty>::MIN as i64)
+                    .insert("maximum", <$ty>::MAX as i64)
+            }
+        }
+    )*}
+}
+
+schema_int!(i8, i16, i32, u8, u16, u32);
+
+impl UclSchema for i64 {
+    fn ucl_schema() -> Builder { typed("integer") }
+}
+
+impl UclSchema for isize {
+    fn ucl_schema() -> Builder { typed("integer") }
+}
+
+impl UclSchema for u64 {
+    fn ucl_schema() -> Builder { typed("integer").insert("minimum", 0) }
+}
+
+impl UclSchema for usize {
+    fn ucl_schema() -> Builder { typed("integer").insert("minimum", 0) }
+}
+
+impl UclSchema for f32 {
+    fn ucl_schema() -> Builder { typed("number") }
+}
+
+impl UclSchema for f64 {
+    fn ucl_schema() -> Builder { typed("number") }
+}
+
+impl UclSchema for bool {
+    fn ucl_schema() -> Builder { typed("boolean") }
+}
+
+impl UclSchema for () {
+    fn ucl_schema() -> Builder { typed("null") }
+}
+
+impl UclSchema for char {
+    fn ucl_schema() -> Builder { typed("string").insert("minLength", 1).insert("maxLength", 1) }
+}
+
+impl UclSchema for str {
+    fn ucl_schema() -> Builder { typed("string") }
+}
+
+impl UclSchema for String {
+    fn ucl_schema() -> Builder { typed("string") }
+}
+
+impl<T: UclSchema> UclSchema for Option<T> {
+    fn ucl_schema() -> Builder {
+        Builder::object().insert("anyOf", Builder::array().push(T::ucl_schema()).push(typed("null")))
+    }
+
+    fn ucl_optional() -> bool {
+        true
+    }
+}
+
+impl<T: UclSchema + ?Sized> UclSchema for &T {
+    fn ucl_schema() -> Builder { T::ucl_schema() }
+    fn ucl_optional() -> bool { T::ucl_optional() }
+}
+
+impl<T: UclSchema + ?Sized> UclSchema for Box<T> {
+    fn ucl_schema() -> Builder { T::ucl_schema() }
+    fn ucl_optional() -> bool { T::ucl_optional() }
+}
+
+impl<T: UclSchema> UclSchema for [T] {
+    fn ucl_schema() -> Builder { typed("array").insert("items", T::ucl_schema()) }
+}
+
+impl<T: UclSchema> UclSchema for Vec<T> {
+    fn ucl_schema() -> Builder { <[T]>::ucl_schema() }
+}
+
+impl<T: UclSchema> UclSchema for HashSet<T> {
+    fn ucl_schema() -> Builder { <[T]>::ucl_schema().insert("uniqueItems", true) }
+}
+
+impl<T: UclSchema> UclSchema for BTreeSet<T> {
+    fn ucl_schema() -> Builder { <[T]>::ucl_schema().insert("uniqueItems", true) }
+}
+
+impl<K, V: UclSchema> UclSchema for HashMap<K, V> {
+    fn ucl_schema() -> Builder { typed("object").insert("additionalProperties", V::ucl_schema()) }
+}
+
+impl<K, V: UclSchema> UclSchema for BTreeMap<K, V> {
+    fn ucl_schema() -> Builder { typed("object").insert("additionalProperties", V::ucl_schema()) }
+}
+
+/// Default resolver reading references from local files.
+fn read_file(base: Option<&PathBuf>, uri: &str) -> Result<Object, UclError> {
+    if uri.contains("://") {
+        return Err(UclError::new(UclErrorType::Io, "remote references are not supported".to_string()))
+    }
+    let path = match base {
+        Some(base) => base.join(uri),
+        None => PathBuf::from(uri),
+    };
+    Parser::new().parse_file(path)
+}
+
+fn load_error(err: UclError, path: Option<String>) -> UclSchemaError {
+    UclSchemaError { code: UclSchemaErrorType::InvalidSchema, desc: err.to_string(), path }
+}
+
+fn invalid(path: &str, desc: String) -> UclSchemaError {
+    violation(UclSchemaErrorType::InvalidSchema, path, desc)
+}
+
+fn violation(code: UclSchemaErrorType, path: &str, desc: String) -> UclSchemaError {
+    UclSchemaError { code, desc, path: Some(path.to_string()) }
+}
+
+fn child(path: &str, key: &str) -> String {
+    if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) }
+}
+
+/// Check that `schema` is well formed, collecting external references together with their paths
+/// and compiling patterns.
+fn check(schema: &Object, path: &str, refs: &mut Vec<(String, String)>,
+         patterns: &mut HashMap<String, Regex>) -> Result<(), UclSchemaError> {
+    if schema.get_type() != Type::Object {
+        return Err(invalid(path, "schema must be an object".to_string()))
+    }
+
+    for (key, value) in schema.entries(true) {
+        let path = child(path, &key);
+        let typ = value.get_type();
+        let fail = |what: &str| Err(invalid(&path, format!("`{}` must be {}", key, what)));
+
+        match key.as_str() {
+            "type" => {
+                let names: Vec<_> = if typ == Type::Array { value.iter().collect() } else { vec![value.new_ref()] };
+                for name in &names {
+                    match name.as_string() {
+                        Some(ref name) if TYPES.contains(&name.as_str()) => {},
+                        _ => return fail("a type name or an array of type names"),
+                    }
+                }
+            },
+            "properties" | "patternProperties" | "definitions" => {
+                if typ != Type::Object {
+                    return fail("an object")
+                }
+                for (name, sub) in value.entries(true) {
+                    let sub_path = child(&path, &name);
+                    if key == "patternProperties" && !compile(&name, patterns) {
+                        return Err(invalid(&sub_path, format!("`{}` is not a valid regular expression", name)))
+                    }
+                    check(&sub, &sub_path, refs, patterns)?;
+                }
+            },
+            "additionalProperties" | "additionalItems" if typ != Type::Boolean => check(&value, &path, refs, patterns)?,
+            "items" => {
+                if typ == Type::Array {
+                    for (i, sub) in value.iter().enumerate() {
+                        check(&sub, &format!("{}[{}]", path, i), refs, patterns)?;
+                    }
+                } else {
+                    check(&value, &path, refs, patterns)?;
+                }
+            },
+            "allOf" | "anyOf" | "oneOf" => {
+                if typ != Type::Array || value.size() == 0 {
+                    return fail("a non-empty array of schemas")
+                }
+                for (i, sub) in value.iter().enumerate() {
+                    check(&sub, &format!("{}[{}]", path, i), refs, patterns)?;
+                }
+            },
+            "not" => check(&value, &path, refs, patterns)?,
+            "dependencies" => {
+                if typ != Type::Object {
+                    return fail("an object")
+                }
+                for (name, dep) in value.entries(true) {
+                    let dep_path = child(&path, &name);
+                    if dep.get_type() == Type::Array {
+                        if dep.iter().any(|name| name.get_type() != Type::String) {
+                            return Err(invalid(&dep_path, "dependency must list property names".to_string()))
+                        }
+                    } else {
+                        check(&dep, &dep_path, refs, patterns)?;
+                    }
+                }
+            },
+            "required" if typ != Type::Array || value.iter().any(|name| name.get_type() != Type::String) => {
+                return fail("an array of property names")
+            },
+            "enum" if typ != Type::Array => return fail("an array"),
+            "maxLength" | "minLength" | "maxItems" | "minItems" | "maxProperties" | "minProperties"
+                if typ != Type::Int || value.as_int().unwrap_or(-1) < 0 => return fail("a non-negative integer"),
+            "minimum" | "maximum" if typ != Type::Int && typ != Type::Float => return fail("a number"),
+            "multipleOf" => {
+                let positive = value.as_int().map(|n| n > 0).or(value.as_float().map(|n| n > 0.0));
+                if positive != Some(true) {
+                    return fail("a positive number")
+                }
+            },
+            "exclusiveMinimum" | "exclusiveMaximum" | "uniqueItems" if typ != Type::Boolean => return fail("a boolean"),
+            "pattern" => match value.as_string() {
+                Some(ref pattern) if !compile(pattern, patterns) => return fail("a valid regular expression"),
+                Some(_) => {},
+                None => return fail("a string"),
+            },
+            "$ref" => {
+                let uri = match value.as_string() {
+                    Some(uri) => uri,
+                    None => return fail("a string"),
+                };
+                let (doc, _) = split_ref(&uri);
+                if !doc.is_empty() {
+                    refs.push((doc.to_string(), path.clone()));
+                }
+            },
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Split `$ref` into document and JSON pointer. Like libucl, only the last `#` separates them.
+fn split_ref(reference: &str) -> (&str, &str) {
+    let mut parts = reference.rsplitn(2, '#');
+    let last = parts.next().unwrap_or_default();
+    match parts.next() {
+        Some(doc) => (doc, last),
+        None => (last, ""),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn invalid_schema_fails_on_load() {
+        let res = Schema::from_str(r#"{"type": "object", "properties": {"key": {"type": "aa"}}}"#);
+        let err = res.err().unwrap();
+        assert_eq!(err.code, UclSchemaErrorType::InvalidSchema);
+        assert_eq!(err.path, Some("properties.key.type".to_string()));
+
+        let err = Schema::from_str(r#"{"required": "key"}"#).err().unwrap();
+        assert_eq!(err.path, Some("required".to_string()));
+        let err = Schema::from_str(r#"{"items": [{"minLength": -1}]}"#).err().unwrap();
+        assert_eq!(err.path, Some("items[0].minLength".to_string()));
+        let err = Schema::from_str("{ key = ").err().unwrap();
+        assert_eq!(err.code, UclSchemaErrorType::InvalidSchema);
+
+        let err = Schema::from_str(r#"{"properties": {"name": {"pattern": "(a"}}}"#).err().unwrap();
+        assert_eq!(err.code, UclSchemaErrorType::InvalidSchema);
+        assert_eq!(err.path, Some("properties.name.pattern".to_string()));
+        let err = Schema::from_str(r#"{"patternProperties": {"[a-": {}}}"#).err().unwrap();
+        assert_eq!(err.path, Some("patternProperties.[a-".to_string()));
+    }
+
+    #[test]
+    fn validate_many() {
+        let schema = Schema::from_str(r#"{"type": "object", "properties": {"key": {"type": "string"}}, "required": ["key"]}"#).unwrap();
+        for i in 0..100 {
+            let doc = Parser::new().parse(format!("key = value{}", i)).unwrap();
+            assert!(schema.validate(&doc).is_ok());
+        }
+        let err = schema.validate(&Parser::new().parse("key = 1").unwrap()).err().unwrap();
+        assert_eq!(err.code, UclSchemaErrorType::TypeMismatch);
+        assert_eq!(err.path, Some("key".to_string()));
+        let err = schema.validate(&Parser::new().parse("other = 1").unwrap()).err().unwrap();
+        assert_eq!(err.code, UclSchemaErrorType::MissingProperty);
+    }
+
+    #[test]
+    fn resolve_references() {
+        let root = Parser::new().parse(r#"{"properties": {
+            "port": {"$ref": "common.json#/definitions/port"},
+            "backup": {"$ref": "common.json#/definitions/port"}
+        }}"#).unwrap();
+        let mut calls = 0;
+        let schema = Schema::with_resolver(root, |uri| {
+            calls += 1;
+            assert_eq!(uri, "common.json");
+            Parser::new().parse(r#"{"definitions": {"port": {"type": "integer"}}}"#)
+        }).unwrap();
+        assert_eq!(calls, 1);
+
+        assert!(schema.validate(&Parser::new().parse("port = 80").unwrap()).is_ok());
+        let err = schema.validate(&Parser::new().parse("backup = http").unwrap()).err().unwrap();
+        assert_eq!(err.code, UclSchemaErrorType::TypeMismatch);
+        assert_eq!(err.path, Some("backup".to_string()));
+    }
+
+    #[test]
+    fn resolve_reference_with_hash_in_name() {
+        let root = Parser::new().parse(r##"{"properties": {"port": {"$ref": "v#2.json#/definitions/port"}}}"##).unwrap();
+        let schema = Schema::with_resolver(root, |uri| {
+            assert_eq!(uri, "v#2.json");
+            Parser::new().parse(r#"{"definitions": {"port": {"type": "integer"}}}"#)
+        }).unwrap();
+
+        assert!(schema.validate(&Parser::new().parse("port = 80").unwrap()).is_ok());
+        assert!(schema.validate(&Parser::new().parse("port = http").unwrap()).is_err());
+    }
+
+    #[test]
+    fn resolve_errors() {
+        let root = Parser::new().parse(r#"{"properties": {"port": {"$ref": "missing.json"}}}"#).unwrap();
+        let err = Schema::with_resolver(root, |uri| Parser::new().parse_file(uri)).err().unwrap();
+        assert_eq!(err.code, UclSchemaErrorType::InvalidSchema);
+        assert_eq!(err.path, Some("properties.port.$ref".to_string()));
+
+        let root = Parser::new().parse(r#"{"$ref": "bad.json"}"#).unwrap();
+        let err = Schema::with_resolver(root, |_| Parser::new().parse(r#"{"type": 1}"#)).err().unwrap();
+        assert_eq!(err.path, Some("bad.json#type".to_string()));
+    }
+
+    #[test]
+    fn from_file() {
+        let dir = ::std::env::temp_dir().join(format!("libucl-schema-{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("root.json"), r#"{"properties": {"port": {"$ref": "port.json"}}}"#).unwrap();
+        fs::write(dir.join("port.json"), r#"{"type": "integer"}"#).unwrap();
+
+        let schema = Schema::from_file(dir.join("root.json"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let schema = schema.unwrap();
+        assert!(schema.validate(&Parser::new().parse("port = 80").unwrap()).is_ok());
+        assert!(schema.validate(&Parser::new().parse("port = http").unwrap()).is_err());
+    }
+
+    #[test]
+    fn validate_all_collects_every_error() {
+        let schema = Schema::from_str(r#"{
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "maxLength": 3},
+                "hosts": {"type": "array", "items": {
+                    "type": "object",
+                    "properties": {"port": {"type": "integer", "maximum": 65535}},
+                    "required": ["port"]
+                }},
+                "mode": {"enum": ["fast", "slow"]}
+            },
+            "required": ["name", "mode"],
+            "additionalProperties": false
+        }"#).unwrap();
+        let doc = Parser::new().parse(r#"
+            name = "toolong";
+            hosts { port = 70000 }
+            hosts { port = "http" }
+            hosts { }
+            extra = 1;
+        "#).unwrap();
+
+        let errors = schema.validate_all(&doc);
+        let found: Vec<_> = errors.iter().map(|err| (err.code.clone(), err.path.clone().unwrap())).collect();
+        assert_eq!(found, vec![
+            (UclSchemaErrorType::Constraint, "name".to_string()),
+            (UclSchemaErrorType::Constraint, "hosts[0].port".to_string()),
+            (UclSchemaErrorType::TypeMismatch, "hosts[1].port".to_string()),
+            (UclSchemaErrorType::MissingProperty, "hosts[2]".to_string()),
+            (UclSchemaErrorType::Constraint, "extra".to_string()),
+            (UclSchemaErrorType::MissingProperty, "".to_string()),
+        ]);
+        assert_eq!(errors[1].element(&doc).unwrap().as_int(), Some(70000));
+        assert_eq!(errors[2].element(&doc).unwrap().as_string(), Some("http".to_string()));
+
+        // Repeated `hosts` stay intact for another run
+        assert_eq!(schema.validate_all(&doc).len(), errors.len());
+        assert_eq!(doc.entries(true).filter(|(key, _)| key == "hosts").count(), 3);
+        drop(doc);
+
+        let doc = Parser::new().parse("name = abc; mode = fast; hosts { port = 80 }").unwrap();
+        assert!(schema.validate_all(&doc).is_empty());
+    }
+
+    #[test]
+    fn validate_all_combinators() {
+        let schema = Schema::from_str(r##"{
+            "definitions": {"small": {"type": "integer", "maximum": 10}},
+            "properties": {
+                "a": {"anyOf": [{"type": "string"}, {"$ref": "#/definitions/small"}]},
+                "b": {"oneOf": [{"type": "integer"}, {"type": "number"}]},
+                "c": {"not": {"type": "string"}},
+                "d": {"type": "array", "uniqueItems": true, "minItems": 3}
+            },
+            "dependencies": {"a": ["b"]}
+        }"##).unwrap();
+
+        let doc = Parser::new().parse("a = 5; b = 1.5; c = 1; d = [1, 2, 3]").unwrap();
+        assert!(schema.validate_all(&doc).is_empty());
+
+        let doc = Parser::new().parse("a = 50; c = x; d = [1, 1]").unwrap();
+        let found: Vec<_> = schema.validate_all(&doc).into_iter().map(|err| (err.code, err.path.unwrap())).collect();
+        assert_eq!(found, vec![
+            (UclSchemaErrorType::Constraint, "a".to_string()),
+            (UclSchemaErrorType::Constraint, "c".to_string()),
+            (UclSchemaErrorType::Constraint, "d[1]".to_string()),
+            (UclSchemaErrorType::Constraint, "d".to_string()),
+            (UclSchemaErrorType::MissingDependency, "".to_string()),
+        ]);
+
+        let doc = Parser::new().parse("b = 1").unwrap();
+        assert_eq!(schema.validate_all(&doc)[0].path, Some("b".to_string()));
+    }
+}
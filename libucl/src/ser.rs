@@ -0,0 +1,510 @@
+//! Serialize Rust data structures into UCL objects.
+//!
+//! Available with `serde` feature.
+//!
+//! Values are built with `object::Builder`. Structs and maps become `Type::Object`, sequences
+//! and tuples become `Type::Array`, `None` and unit become `Type::Null`. Enums are written the
+//! same way `de` reads them: unit variants as strings, other variants as object with single key.
+
+use std::error;
+use std::fmt;
+
+use serde::ser::{self, Serialize};
+
+use object::{Builder, Emitter, Object, Type};
+
+/// Serialization error.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error {
+    msg: String,
+}
+
+impl Error {
+    /// Error message.
+    pub fn message(&self) -> &str {
+        self.msg.as_ref()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+impl error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error {
+            msg: msg.to_string(),
+        }
+    }
+}
+
+/// Convert `value` into UCL object.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::collections::BTreeMap;
+///
+/// let mut ports = BTreeMap::new();
+/// ports.insert("http", 80);
+/// ports.insert("https", 443);
+///
+/// let obj = libucl::to_object(&ports).unwrap();
+/// assert_eq!(obj.fetch("https").unwrap().as_int(), Some(443));
+/// ```
+pub fn to_object<T: Serialize + ?Sized>(value: &T) -> Result<Object, Error> {
+    value.serialize(Serializer)
+}
+
+/// Convert `value` into UCL object and emit it in given text format.
+///
+/// Binary `Emitter::MsgPack` is refused, use `to_vec` for it.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::collections::BTreeMap;
+/// use libucl::Emitter;
+///
+/// let mut ports = BTreeMap::new();
+/// ports.insert("http", 80);
+///
+/// assert_eq!(libucl::to_string(&ports, Emitter::JSONCompact).unwrap(), r#"{"http":80}"#);
+/// ```
+pub fn to_string<T: Serialize + ?Sized>(value: &T, emitter: Emitter) -> Result<String, Error> {
+    if emitter == Emitter::MsgPack {
+        return Err(ser::Error::custom("MsgPack output is binary, use `to_vec` instead"))
+    }
+    to_object(value).map(|obj| obj.dump_into(emitter))
+}
+
+/// Convert `value` into UCL object and emit it in given format as raw bytes.
+///
+/// # Examples
+///
+/// ```rust
+/// use libucl::Emitter;
+///
+/// assert_eq!(libucl::to_vec(&[1, 2], Emitter::MsgPack).unwrap(), vec![0x92, 0x01, 0x02]);
+/// ```
+pub fn to_vec<T: Serialize + ?Sized>(value: &T, emitter: Emitter) -> Result<Vec<u8>, Error> {
+    to_object(value).map(|obj| obj.emit_bytes(emitter))
+}
+
+/// Serde serializer producing `Object`.
+pub struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Object;
+    type Error = Error;
+
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeArray;
+    type SerializeMap = SerializeObject;
+    type SerializeStruct = SerializeObject;
+    type SerializeStructVariant = SerializeObject;
+
+    fn serialize_bool(self, v: bool) -> Result<Object, Error> {
+        Ok(Builder::from(v).build())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Object, Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Object, Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Object, Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Object, Error> {
+        Ok(Builder::from(v).build())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Object, Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Object, Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Object, Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Object, Error> {
+        if v > i64::MAX as u64 {
+            return Err(ser::Error::custom(format!("integer `{}` does not fit into i64", v)))
+        }
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Object, Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Object, Error> {
+        Ok(Builder::from(v).build())
+    }
+
+    fn serialize_char(self, v: char) -> Result<Object, Error> {
+        self.serialize_str(&v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Object, Error> {
+        Ok(Builder::from(v).build())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Object, Error> {
+        Ok(Builder::from(v.iter().map(|&b| b as i64).collect::<Vec<_>>()).build())
+    }
+
+    fn serialize_none(self) -> Result<Object, Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Object, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Object, Error> {
+        Ok(Builder::null().build())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Object, Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str)
+                              -> Result<Object, Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T)
+                                                       -> Result<Object, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32,
+                                                        variant: &'static str, value: &T)
+                                                        -> Result<Object, Error> {
+        let value = value.serialize(Serializer)?;
+        Ok(Builder::object().insert(variant, value).build())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<SerializeArray, Error> {
+        Ok(SerializeArray::new(None))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeArray, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, variant: &'static str, _len: usize)
+                               -> Result<SerializeArray, Error> {
+        Ok(SerializeArray::new(Some(variant)))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeObject, Error> {
+        Ok(SerializeObject::new(None))
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeObject, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str, _len: usize)
+                                -> Result<SerializeObject, Error> {
+        Ok(SerializeObject::new(Some(variant)))
+    }
+}
+
+/// Wrap `value` into object with single `variant` key.
+fn wrap_variant(value: Object, variant: Option<&'static str>) -> Object {
+    match variant {
+        Some(variant) => Builder::object().insert(variant, value).build(),
+        None => value
+    }
+}
+
+/// Serializer of sequences, tuples and tuple variants.
+pub struct SerializeArray {
+    arr: Object,
+    variant: Option<&'static str>,
+}
+
+impl SerializeArray {
+    fn new(variant: Option<&'static str>) -> Self {
+        SerializeArray {
+            arr: Builder::array().build(),
+            variant,
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let value = value.serialize(Serializer)?;
+        self.arr.append(value);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Object, Error> {
+        Ok(wrap_variant(self.arr, self.variant))
+    }
+}
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = Object;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Object, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = Object;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Object, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = Object;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Object, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeArray {
+    type Ok = Object;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Object, Error> {
+        self.finish()
+    }
+}
+
+/// Serializer of maps, structs and struct variants.
+pub struct SerializeObject {
+    obj: Object,
+    key: Option<String>,
+    variant: Option<&'static str>,
+}
+
+impl SerializeObject {
+    fn new(variant: Option<&'static str>) -> Self {
+        SerializeObject {
+            obj: Builder::object().build(),
+            key: None,
+            variant,
+        }
+    }
+
+    fn insert<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), Error> {
+        if key.contains('\0') {
+            return Err(ser::Error::custom(format!("key `{}` contains NUL byte", key.escape_default())))
+        }
+
+        let value = value.serialize(Serializer)?;
+        self.obj.insert_key(key, value);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Object, Error> {
+        Ok(wrap_variant(self.obj, self.variant))
+    }
+}
+
+impl ser::SerializeMap for SerializeObject {
+    type Ok = Object;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        let key = key.serialize(Serializer)?;
+        let key = match key.get_type() {
+            Type::String => key.as_string(),
+            Type::Int => key.as_int().map(|k| k.to_string()),
+            Type::Boolean => key.as_bool().map(|k| k.to_string()),
+            _ => None
+        };
+
+        match key {
+            Some(key) => {
+                self.key = Some(key);
+                Ok(())
+            },
+            None => Err(ser::Error::custom("map key must be a string, integer or boolean"))
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().expect("serialize_value called before serialize_key");
+        self.insert(&key, value)
+    }
+
+    fn end(self) -> Result<Object, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SerializeObject {
+    type Ok = Object;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Object, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeObject {
+    type Ok = Object;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Object, Error> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use de::{from_object, from_str};
+    use parser::{ParseType, Parser};
+    use super::*;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Action {
+        Reject,
+        AddHeader(String),
+        Rewrite { subject: String },
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Worker {
+        bind_socket: Vec<String>,
+        count: u32,
+        timeout: f64,
+        enabled: bool,
+        group: Option<String>,
+        actions: Vec<Action>,
+        limits: BTreeMap<String, i64>,
+    }
+
+    fn worker() -> Worker {
+        let mut limits = BTreeMap::new();
+        limits.insert("max_conn".to_string(), 1024);
+
+        Worker {
+            bind_socket: vec!["localhost:11333".to_string(), "*:11334".to_string()],
+            count: 4,
+            timeout: 2.5,
+            enabled: true,
+            group: None,
+            actions: vec![
+                Action::Reject,
+                Action::AddHeader("X-Spam".to_string()),
+                Action::Rewrite { subject: "[SPAM] %s".to_string() },
+            ],
+            limits,
+        }
+    }
+
+    #[test]
+    fn to_object_structure() {
+        let obj = to_object(&worker()).unwrap();
+
+        assert_eq!(obj.get_type(), Type::Object);
+        assert_eq!(obj.fetch_path("bind_socket.1").unwrap().as_string(), Some("*:11334".to_string()));
+        assert_eq!(obj.fetch("count").unwrap().as_int(), Some(4));
+        assert_eq!(obj.fetch("group").unwrap().get_type(), Type::Null);
+        assert_eq!(obj.fetch_path("actions.0").unwrap().as_string(), Some("reject".to_string()));
+        assert_eq!(obj.fetch_path("actions.2.rewrite.subject").unwrap().as_string(),
+                   Some("[SPAM] %s".to_string()));
+        assert_eq!(obj.fetch_path("limits.max_conn").unwrap().as_int(), Some(1024));
+    }
+
+    #[test]
+    fn round_trip() {
+        for emitter in [Emitter::Config, Emitter::JSON, Emitter::JSONCompact] {
+            let text = to_string(&worker(), emitter).unwrap();
+            assert_eq!(from_str::<Worker>(&text).unwrap(), worker());
+        }
+    }
+
+    #[test]
+    fn binary_output() {
+        let err = to_string(&worker(), Emitter::MsgPack).unwrap_err();
+        assert_eq!(err.message(), "MsgPack output is binary, use `to_vec` instead");
+
+        let blob = to_vec(&worker(), Emitter::MsgPack).unwrap();
+        let obj = Parser::new().parse_as(&blob, ParseType::Msgpack).unwrap();
+        assert_eq!(from_object::<Worker>(&obj).unwrap(), worker());
+    }
+
+    #[test]
+    fn map_keys() {
+        let mut map = BTreeMap::new();
+        map.insert(1, "a");
+        map.insert(2, "b");
+        assert_eq!(to_string(&map, Emitter::JSONCompact).unwrap(), r#"{"1":"a","2":"b"}"#);
+
+        let mut map = BTreeMap::new();
+        map.insert((1, 2), "a");
+        assert!(to_object(&map).is_err());
+
+        let mut map = BTreeMap::new();
+        map.insert("a\0b", 1);
+        assert!(to_object(&map).is_err());
+        assert_eq!(to_object(&"a\0b").unwrap().as_string(), Some("a\0b".to_string()));
+    }
+
+    #[test]
+    fn integer_out_of_range() {
+        let err = to_object(&u64::MAX).unwrap_err();
+        assert_eq!(err.message(), "integer `18446744073709551615` does not fit into i64");
+    }
+}
@@ -1,9 +1,9 @@
 use libc::c_char;
 
-use std::str;
 use std::ffi::CStr;
 
+/// Copy C string, replacing invalid UTF-8 sequences (libucl does not validate input) with U+FFFD.
 pub fn to_str(cstring: *const c_char) -> Option<String> {
     if cstring.is_null() { return None }
-    Some(str::from_utf8(unsafe { CStr::from_ptr(cstring).to_bytes() }).unwrap().to_string())
+    Some(unsafe { CStr::from_ptr(cstring) }.to_string_lossy().into_owned())
 }
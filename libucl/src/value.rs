@@ -0,0 +1,349 @@
+//! Owned representation of UCL documents.
+//!
+//! `Value` holds a copy of an object tree independent of libucl, so it can outlive the parser,
+//! cross threads and be compared.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use libucl::Value;
+//!
+//! let doc = libucl::Parser::new().parse("name = web; timeout = 5s; ports = [80, 443]").unwrap();
+//! let value = Value::from(&doc);
+//!
+//! assert_eq!(value.get("name"), Some(&Value::String("web".to_string())));
+//! assert_eq!(value.get("timeout"), Some(&Value::Time(Duration::from_secs(5))));
+//! assert_eq!(value.get("ports"), Some(&Value::Array(vec![Value::Int(80), Value::Int(443)])));
+//! ```
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+pub use indexmap::IndexMap;
+
+use error::UclConversionError;
+use object::{iter, Builder, Object, Type};
+
+/// Owned UCL value.
+///
+/// Objects keep keys in document order, values of repeated keys being collected into
+/// `Value::Array`. Objects compare equal regardless of key order. User data is converted to
+/// `Value::Null`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    /// Time value, like `10s`. Negative times are stored as `Value::Float` seconds.
+    Time(Duration),
+    Array(Vec<Value>),
+    Object(IndexMap<String, Value>),
+}
+
+impl Value {
+    /// Value stored under `key`, `None` for missing keys and other types than `Value::Object`.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match *self {
+            Value::Object(ref entries) => entries.get(key),
+            _ => None
+        }
+    }
+
+    /// Whether value is `Value::Null`.
+    pub fn is_null(&self) -> bool {
+        *self == Value::Null
+    }
+}
+
+impl<'a> From<&'a Object> for Value {
+    fn from(obj: &'a Object) -> Self {
+        match obj.get_type() {
+            Type::Object => Value::Object(iter::groups(obj).into_iter().map(|(key, mut values)| {
+                let value = if values.len() == 1 {
+                    Value::from(&values.remove(0))
+                } else {
+                    Value::Array(values.iter().map(Value::from).collect())
+                };
+                (key, value)
+            }).collect()),
+            Type::Array => Value::Array(obj.iter().map(|value| Value::from(&value)).collect()),
+            Type::Int => Value::Int(obj.as_int().unwrap_or_default()),
+            Type::Float => Value::Float(obj.as_float().unwrap_or_default()),
+            Type::Time => match obj.as_duration() {
+                Some(duration) => Value::Time(duration),
+                None => Value::Float(obj.as_seconds().unwrap_or_default()),
+            },
+            Type::String => Value::String(obj.as_string().unwrap_or_default()),
+            Type::Boolean => Value::Bool(obj.as_bool().unwrap_or_default()),
+            Type::UserData | Type::Null => Value::Null,
+        }
+    }
+}
+
+impl From<Object> for Value {
+    fn from(obj: Object) -> Self {
+        Value::from(&obj)
+    }
+}
+
+/// Fails for object keys containing NUL bytes, which libucl cannot store.
+impl TryFrom<Value> for Builder {
+    type Error = UclConversionError;
+
+    fn try_from(value: Value) -> Result<Self, UclConversionError> {
+        let builder = match value {
+            Value::Null => Builder::null(),
+            Value::Bool(b) => Builder::from(b),
+            Value::Int(n) => Builder::from(n),
+            Value::Float(n) => Builder::from(n),
+            Value::String(s) => Builder::from(s),
+            Value::Time(duration) => Builder::from(duration),
+            Value::Array(items) => {
+                let mut arr = Builder::array();
+                for item in items {
+                    arr = arr.push(Builder::try_from(item)?);
+                }
+                arr
+            },
+            Value::Object(entries) => {
+                let mut obj = Builder::object();
+                for (key, value) in entries {
+                    if key.contains('\0') {
+                        let desc = format!("key `{}` contains NUL byte", key.escape_default());
+                        return Err(UclConversionError::new(Type::Object, "Builder", desc))
+                    }
+                    obj = obj.insert(key, Builder::try_from(value)?);
+                }
+                obj
+            },
+        };
+        Ok(builder)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use std::fmt;
+
+    use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+    use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+
+    use super::{IndexMap, Value};
+
+    /// Times are serialized as `f64` number of seconds, the same way `from_object` reads them.
+    impl Serialize for Value {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            match *self {
+                Value::Null => serializer.serialize_unit(),
+                Value::Bool(b) => serializer.serialize_bool(b),
+                Value::Int(n) => serializer.serialize_i64(n),
+                Value::Float(n) => serializer.serialize_f64(n),
+                Value::String(ref s) => serializer.serialize_str(s),
+                Value::Time(ref duration) => serializer.serialize_f64(duration.as_secs_f64()),
+                Value::Array(ref items) => {
+                    let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                    for item in items {
+                        seq.serialize_element(item)?;
+                    }
+                    seq.end()
+                },
+                Value::Object(ref entries) => {
+                    let mut map = serializer.serialize_map(Some(entries.len()))?;
+                    for (key, value) in entries {
+                        map.serialize_entry(key, value)?;
+                    }
+                    map.end()
+                },
+            }
+        }
+    }
+
+    struct ValueVisitor;
+
+    impl<'de> Visitor<'de> for ValueVisitor {
+        type Value = Value;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("any UCL value")
+        }
+
+        fn visit_bool<E>(self, b: bool) -> Result<Value, E> {
+            Ok(Value::Bool(b))
+        }
+
+        fn visit_i64<E>(self, n: i64) -> Result<Value, E> {
+            Ok(Value::Int(n))
+        }
+
+        fn visit_u64<E: de::Error>(self, n: u64) -> Result<Value, E> {
+            if n <= i64::MAX as u64 {
+                Ok(Value::Int(n as i64))
+            } else {
+                Err(E::invalid_value(de::Unexpected::Unsigned(n), &"integer fitting into i64"))
+            }
+        }
+
+        fn visit_f64<E>(self, n: f64) -> Result<Value, E> {
+            Ok(Value::Float(n))
+        }
+
+        fn visit_str<E>(self, s: &str) -> Result<Value, E> {
+            Ok(Value::String(s.to_string()))
+        }
+
+        fn visit_string<E>(self, s: String) -> Result<Value, E> {
+            Ok(Value::String(s))
+        }
+
+        fn visit_unit<E>(self) -> Result<Value, E> {
+            Ok(Value::Null)
+        }
+
+        fn visit_none<E>(self) -> Result<Value, E> {
+            Ok(Value::Null)
+        }
+
+        fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+            Deserialize::deserialize(deserializer)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+            let mut items = Vec::new();
+            while let Some(item) = seq.next_element()? {
+                items.push(item);
+            }
+            Ok(Value::Array(items))
+        }
+
+        /// Repeated keys keep their first position and the last value.
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+            let mut entries = IndexMap::new();
+            while let Some((key, value)) = map.next_entry()? {
+                entries.insert(key, value);
+            }
+            Ok(Value::Object(entries))
+        }
+    }
+
+    /// Numbers are read as `Value::Int` or `Value::Float`, never as `Value::Time`.
+    impl<'de> Deserialize<'de> for Value {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+            deserializer.deserialize_any(ValueVisitor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use parser::Parser;
+    use object::{EmitOptions, Emitter};
+    use super::*;
+
+    static DOC: &str = r#"
+        name = web;
+        enabled = true;
+        ratio = 0.5;
+        timeout = 1.5min;
+        backlog = null;
+        upstream { host = a; port = 80 }
+        upstream { host = b; port = 81 }
+        labels { zone = east; tier = front }
+    "#;
+
+    #[test]
+    fn from_object() {
+        let value = Value::from(Parser::new().parse(DOC).unwrap());
+
+        let keys: Vec<_> = match value {
+            Value::Object(ref entries) => entries.keys().map(String::as_str).collect(),
+            _ => panic!("not an object"),
+        };
+        assert_eq!(keys, vec!["name", "enabled", "ratio", "timeout", "backlog", "upstream", "labels"]);
+        assert_eq!(value.get("enabled"), Some(&Value::Bool(true)));
+        assert_eq!(value.get("ratio"), Some(&Value::Float(0.5)));
+        assert_eq!(value.get("timeout"), Some(&Value::Time(Duration::from_secs(90))));
+        assert!(value.get("backlog").unwrap().is_null());
+        assert_eq!(value.get("labels"), Some(&Value::Object(vec![
+            ("zone".to_string(), Value::String("east".to_string())),
+            ("tier".to_string(), Value::String("front".to_string())),
+        ].into_iter().collect())));
+        match value.get("upstream") {
+            Some(Value::Array(items)) => assert_eq!(items[1].get("port"), Some(&Value::Int(81))),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn from_object_repeated() {
+        let doc = Parser::new().parse(DOC).unwrap();
+        let dump = doc.dump();
+
+        let value = Value::from(&doc);
+        assert_eq!(Value::from(&doc), value);
+        assert_eq!(doc.dump(), dump);
+        assert_eq!(doc.entries(true).filter(|(key, _)| key == "upstream").count(), 2);
+    }
+
+    #[test]
+    fn out_of_range_time() {
+        let value = Value::from(Parser::new().parse("t = 1e30s; n = -2s").unwrap());
+        assert_eq!(value.get("t"), Some(&Value::Float(1e30)));
+        assert_eq!(value.get("n"), Some(&Value::Float(-2.0)));
+    }
+
+    #[test]
+    fn round_trip() {
+        let value = Value::from(Parser::new().parse(DOC).unwrap());
+        let obj = Builder::try_from(value.clone()).unwrap().build();
+        assert_eq!(obj.fetch("timeout").unwrap().get_type(), Type::Time);
+        assert_eq!(Value::from(&obj), value);
+
+        let text = EmitOptions::new(Emitter::Config).human_durations(true).emit(&obj).unwrap();
+        assert_eq!(Value::from(Parser::new().parse(text).unwrap()), value);
+    }
+
+    #[test]
+    fn nul_bytes() {
+        let value = Value::Object(vec![("a".to_string(), Value::String("x\0y".to_string()))].into_iter().collect());
+        let obj = Builder::try_from(value).unwrap().build();
+        assert_eq!(obj.fetch("a").unwrap().as_string(), Some("x\0y".to_string()));
+
+        let value = Value::Object(vec![("a\0b".to_string(), Value::Null)].into_iter().collect());
+        assert!(Builder::try_from(value).is_err());
+    }
+
+    #[test]
+    fn send_to_thread() {
+        let value = Value::from(Parser::new().parse(DOC).unwrap());
+        let name = thread::spawn(move || value.get("name").cloned()).join().unwrap();
+        assert_eq!(name, Some(Value::String("web".to_string())));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        use serde::Deserialize;
+        use serde::de::value::MapDeserializer;
+
+        let value = Value::from(Parser::new().parse(DOC).unwrap());
+        let text = ::to_string(&value, Emitter::JSON).unwrap();
+
+        let read: Value = ::from_str(&text).unwrap();
+        assert_eq!(read.get("timeout"), Some(&Value::Float(90.0)));
+        assert_eq!(read.get("upstream"), value.get("upstream"));
+        assert_eq!(read.get("backlog"), Some(&Value::Null));
+
+        let pairs = vec![("a", 1), ("b", 2), ("a", 3)];
+        let map = MapDeserializer::<_, ::serde::de::value::Error>::new(pairs.into_iter());
+        let read = Value::deserialize(map).unwrap();
+        let expected: IndexMap<_, _> = vec![("a".to_string(), Value::Int(3)), ("b".to_string(), Value::Int(2))]
+            .into_iter()
+            .collect();
+        assert_eq!(read, Value::Object(expected));
+    }
+}
@@ -0,0 +1,131 @@
+//! `#[derive(UclSchema)]` expands to paths of `libucl`, so it is tested as external crate.
+#![cfg(feature = "derive")]
+
+extern crate libucl;
+
+use std::collections::HashMap;
+
+use libucl::{Parser, Schema, UclSchema};
+use libucl::error::UclSchemaErrorType;
+
+/// Upstream server.
+#[derive(UclSchema)]
+#[allow(dead_code)]
+struct Upstream {
+    /// Host name or address.
+    host: String,
+    port: u16,
+    weight: Option<u32>,
+}
+
+#[derive(UclSchema)]
+#[serde(rename_all = "kebab-case")]
+#[allow(dead_code)]
+struct Service {
+    service_name: String,
+    upstreams: Vec<Upstream>,
+    #[serde(default)]
+    labels: HashMap<String, String>,
+    #[serde(rename = "lb")]
+    balancer: Balancer,
+    #[serde(skip)]
+    state: (),
+}
+
+#[derive(UclSchema)]
+#[serde(rename_all = "snake_case")]
+#[allow(dead_code)]
+enum Balancer {
+    RoundRobin,
+    Random,
+    /// Hash of request header.
+    Hash(String),
+    Weighted { seed: i64 },
+}
+
+#[derive(UclSchema)]
+#[allow(dead_code)]
+struct Pair<T>(T, T);
+
+#[derive(UclSchema)]
+#[serde(rename_all = "UPPERCASE")]
+#[allow(dead_code)]
+struct Upper {
+    service_name: String,
+    _private: bool,
+    r#type: String,
+}
+
+#[derive(UclSchema)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code, non_snake_case)]
+struct Camel {
+    service_name: String,
+    retry__count: u32,
+}
+
+#[derive(UclSchema)]
+#[serde(rename_all = "SCREAMING-KEBAB-CASE")]
+#[allow(dead_code)]
+enum Kebab {
+    RoundRobin,
+    r#Random,
+}
+
+#[test]
+fn struct_schema() {
+    let schema = Upstream::ucl_schema().build();
+    assert_eq!(schema.fetch("description").unwrap().as_string(), Some("Upstream server.".to_string()));
+    assert_eq!(schema.fetch_path("properties.host.description").unwrap().as_string(),
+               Some("Host name or address.".to_string()));
+    let required: Vec<_> = schema.fetch("required").unwrap().iter().filter_map(|r| r.as_string()).collect();
+    assert_eq!(required, vec!["host", "port"]);
+
+    let schema = Schema::of::<Upstream>().unwrap();
+    assert!(schema.validate(&Parser::new().parse("host = a; port = 80").unwrap()).is_ok());
+    assert!(schema.validate(&Parser::new().parse("host = a; port = 80; weight = 2").unwrap()).is_ok());
+    let err = schema.validate(&Parser::new().parse("host = a; port = 70000").unwrap()).unwrap_err();
+    assert_eq!(err.path, Some("port".to_string()));
+}
+
+#[test]
+fn attributes_and_enums() {
+    let schema = Schema::of::<Service>().unwrap();
+    let doc = Parser::new().parse(r#"
+        service-name = web;
+        upstreams [ { host = a; port = 80 } ]
+        lb = random;
+    "#).unwrap();
+    assert!(schema.validate(&doc).is_ok());
+
+    for lb in &["lb { hash = x-user }", "lb { weighted { seed = 1 } }", "lb = round-robin"] {
+        let doc = Parser::new().parse(format!("service-name = web; upstreams []; {}", lb)).unwrap();
+        assert_eq!(schema.validate(&doc).is_ok(), lb != &"lb = round-robin", "{}", lb);
+    }
+
+    let doc = Parser::new().parse("service_name = web; upstreams []; lb = Random; state = null").unwrap();
+    let codes: Vec<_> = schema.validate_all(&doc).into_iter().map(|err| err.code).collect();
+    assert_eq!(codes, vec![UclSchemaErrorType::Constraint, UclSchemaErrorType::MissingProperty]);
+}
+
+#[test]
+fn generic_tuple() {
+    let schema = Schema::of::<Pair<bool>>().unwrap();
+    assert!(schema.validate(&Parser::new().parse("[true, false]").unwrap()).is_ok());
+    assert!(schema.validate(&Parser::new().parse("[true]").unwrap()).is_err());
+    assert!(schema.validate(&Parser::new().parse("[true, 1]").unwrap()).is_err());
+}
+
+#[test]
+fn rename_rules() {
+    let names = |schema: libucl::object::Builder| -> Vec<String> {
+        schema.build().fetch("properties").unwrap().keys().collect()
+    };
+    assert_eq!(names(Upper::ucl_schema()), vec!["SERVICE_NAME", "_PRIVATE", "TYPE"]);
+    assert_eq!(names(Camel::ucl_schema()), vec!["serviceName", "retryCount"]);
+
+    let variants: Vec<_> = Kebab::ucl_schema().build().fetch("enum").unwrap().iter()
+        .filter_map(|v| v.as_string())
+        .collect();
+    assert_eq!(variants, vec!["ROUND-ROBIN", "RANDOM"]);
+}